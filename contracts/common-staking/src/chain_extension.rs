@@ -0,0 +1,40 @@
+pub use extension_errors::StakingExtensionError;
+use ink::env::{DefaultEnvironment, Environment};
+
+/// Chain extension that provides information about the staking configuration and lets the contract
+/// stake its own funds.
+///
+/// `stake` and `stake_more` return `Err(StakingExtensionError)` when the runtime reports a failure.
+#[ink::chain_extension(extension = 0)]
+pub trait StakingExtension {
+    type ErrorCode = StakingExtensionError;
+
+    /// Returns the minimum stake required to become a staker.
+    #[ink(function = 42, handle_status = false)]
+    fn min_stake() -> u128;
+
+    /// Stakes `amount` of the free funds of the calling contract.
+    #[ink(function = 49)]
+    fn stake(amount: u128);
+
+    /// Increases the stake of the calling contract by `amount` of its free funds.
+    #[ink(function = 50)]
+    fn stake_more(amount: u128);
+}
+
+/// Default ink environment with `StakingExtension` included.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum StakingEnvironment {}
+
+impl Environment for StakingEnvironment {
+    const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <DefaultEnvironment as Environment>::AccountId;
+    type Balance = <DefaultEnvironment as Environment>::Balance;
+    type Hash = <DefaultEnvironment as Environment>::Hash;
+    type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+    type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+
+    type ChainExtension = StakingExtension;
+}
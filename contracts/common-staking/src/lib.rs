@@ -1,15 +1,16 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
+mod chain_extension;
 mod runtime_call;
 
-pub const THRESHOLD: u128 = 100;
-
 /// Common staking contract allows users to deposit funds that will be accumulated and then staked
 /// together in the `FakeStaking` pallet.
-#[ink::contract]
+///
+/// The minimum stake is not hard-coded: it is read from the runtime with
+/// `self.env().extension().min_stake()`.
+#[ink::contract(env = crate::chain_extension::StakingEnvironment)]
 mod common_staking {
     use crate::runtime_call::{FakeStakingCall, RuntimeCall};
-    use crate::THRESHOLD;
 
     #[ink(storage)]
     pub struct CommonStaking {
@@ -27,17 +28,23 @@ mod common_staking {
 
         /// Deposits the transferred balance into the contract.
         /// 
-        /// 1. If the accumulated balance is now greater than or equal to `THRESHOLD`, then the
-        /// contract will call the `FakeStaking` pallet to stake the accumulated balance.
-        /// 2. If the accumulated balance is greater than or equal to `THRESHOLD` and the contract
-        /// is already staking, then the contract will call the `FakeStaking` pallet to increase the
-        /// stake by the transferred balance.
-        /// 3. If the accumulated balance is less than `THRESHOLD`, then the contract will just
-        /// accumulate the transferred balance.
+        /// 1. If the accumulated balance is now greater than or equal to the minimum stake, then
+        /// the contract will call the `FakeStaking` pallet to stake the accumulated balance.
+        /// 2. If the accumulated balance is greater than or equal to the minimum stake and the
+        /// contract is already staking, then the contract will call the `FakeStaking` pallet to
+        /// increase the stake by the transferred balance.
+        /// 3. If the accumulated balance is less than the minimum stake, then the contract will
+        /// just accumulate the transferred balance.
         #[ink(message, payable, selector = 1)]
         pub fn stake(&mut self) {
             // todo: implement
         }
+
+        /// Returns the current minimum stake of the `FakeStaking` pallet.
+        #[ink(message)]
+        pub fn min_stake(&self) -> u128 {
+            self.env().extension().min_stake()
+        }
     }
 
     impl Default for CommonStaking {
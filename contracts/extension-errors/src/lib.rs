@@ -0,0 +1,75 @@
+//! The errors of the chain extensions of the sandbox runtime, decoded from their status codes.
+//!
+//! They are shared by all the contracts that use the extensions, so that each of them reports the
+//! same typed errors.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink::env::chain_extension::FromStatusCode;
+
+/// Errors that can be returned by the `StakingExtension`, decoded from its status codes.
+///
+/// The status codes are defined by the runtime side of the extension
+/// (`sandbox_with_staking::chain_extension::StakingExtensionError`).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum StakingExtensionError {
+    /// The runtime does not know the called function.
+    UnknownFunction,
+    /// The staking system has rejected the call for a reason not covered by the other variants.
+    DispatchFailed,
+    /// The runtime could not decode the input of the function.
+    DecodingFailed,
+    /// The stake is not enough to become a staker.
+    NotEnoughStake,
+    /// The account is already a staker.
+    AlreadyStaking,
+    /// The account is not a staker.
+    NotStaker,
+    /// The free balance of the account is not enough to cover the stake.
+    InsufficientBalance,
+    /// Too many accounts have been passed to a batch function.
+    TooManyAccounts,
+    /// A status code that this version of the contract does not know.
+    Other(u32),
+}
+
+impl FromStatusCode for StakingExtensionError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            1 => Err(Self::UnknownFunction),
+            2 => Err(Self::DispatchFailed),
+            3 => Err(Self::DecodingFailed),
+            4 => Err(Self::NotEnoughStake),
+            5 => Err(Self::AlreadyStaking),
+            6 => Err(Self::NotStaker),
+            7 => Err(Self::InsufficientBalance),
+            8 => Err(Self::TooManyAccounts),
+            _ => Err(Self::Other(status_code)),
+        }
+    }
+}
+
+/// Errors that can be returned by the `RandomnessExtension`, decoded from its status codes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum RandomnessExtensionError {
+    /// The runtime does not know the called function.
+    UnknownFunction,
+    /// The runtime could not decode the input of the function.
+    DecodingFailed,
+    /// A status code that this version of the contract does not know.
+    Other(u32),
+}
+
+impl FromStatusCode for RandomnessExtensionError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            1 => Err(Self::UnknownFunction),
+            3 => Err(Self::DecodingFailed),
+            _ => Err(Self::Other(status_code)),
+        }
+    }
+}
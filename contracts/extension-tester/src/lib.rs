@@ -0,0 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// A helper contract for testing the chain extensions of the sandbox runtime. Every message makes
+/// a single extension call and returns what the contract got back, so the tests can check how the
+/// runtime side answers real contract calls.
+#[ink::contract(env = weighted_voting::chain_extension::StakingEnvironment)]
+mod extension_tester {
+    use ink::env::chain_extension::ChainExtensionMethod;
+    use weighted_voting::chain_extension::{RandomnessExtensionError, StakingExtensionError};
+
+    #[ink(storage)]
+    pub struct ExtensionTester {}
+
+    impl ExtensionTester {
+        /// Creates a new `ExtensionTester` contract.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {}
+        }
+
+        /// Calls the function `func_id` of the staking extension without any input and returns
+        /// the decoded status code. Unlike the typed `StakingExtension`, it can call function IDs
+        /// that the runtime does not know.
+        #[ink(message)]
+        pub fn call_staking_function(&self, func_id: u16) -> Result<(), StakingExtensionError> {
+            // The staking extension is registered under the ID `0`, so the function ID is the
+            // whole extension method ID.
+            ChainExtensionMethod::build(u32::from(func_id))
+                .input::<()>()
+                .output::<(), false>()
+                .handle_error_code::<StakingExtensionError>()
+                .call(&())
+        }
+
+        /// Reads the minimum stake `times` times. Every read is a separate extension call, so the
+        /// gas consumed by the message grows with `times`.
+        #[ink(message)]
+        pub fn read_min_stake(&self, times: u32) -> Result<u128, StakingExtensionError> {
+            let mut min_stake = 0;
+            for _ in 0..times {
+                min_stake = self.env().extension().staking.min_stake()?;
+            }
+            Ok(min_stake)
+        }
+
+        /// Stakes `amount` of the free funds of the contract.
+        #[ink(message)]
+        pub fn stake(&mut self, amount: u128) -> Result<(), StakingExtensionError> {
+            self.env().extension().staking.stake(amount)
+        }
+
+        /// Increases the stake of the contract by `amount` of its free funds.
+        #[ink(message)]
+        pub fn stake_more(&mut self, amount: u128) -> Result<(), StakingExtensionError> {
+            self.env().extension().staking.stake_more(amount)
+        }
+
+        /// Calls the function `func_id` of the randomness extension without any input and returns
+        /// the decoded status code.
+        #[ink(message)]
+        pub fn call_randomness_function(
+            &self,
+            func_id: u16,
+        ) -> Result<(), RandomnessExtensionError> {
+            // The randomness extension is registered under the ID `1`, which takes the upper half
+            // of the extension method ID.
+            ChainExtensionMethod::build((1 << 16) | u32::from(func_id))
+                .input::<()>()
+                .output::<(), false>()
+                .handle_error_code::<RandomnessExtensionError>()
+                .call(&())
+        }
+
+        /// Returns the random value for `subject` in the current block.
+        #[ink(message)]
+        pub fn random(&self, subject: [u8; 32]) -> Result<[u8; 32], RandomnessExtensionError> {
+            self.env().extension().randomness.random(subject)
+        }
+    }
+
+    impl Default for ExtensionTester {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
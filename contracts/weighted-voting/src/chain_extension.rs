@@ -1,33 +1,125 @@
+pub use extension_errors::{RandomnessExtensionError, StakingExtensionError};
 use ink::{
-    env::{chain_extension::FromStatusCode, DefaultEnvironment, Environment},
+    env::{DefaultEnvironment, Environment},
+    prelude::vec::Vec,
     primitives::AccountId,
 };
 
-/// Simple chain extension that provides information about the validator status.
+/// Simple chain extension that provides information about the validator status and staking.
+///
+/// Every function returns `Err(StakingExtensionError)` when the runtime reports a failure.
 #[ink::chain_extension(extension = 0)]
 pub trait StakingExtension {
-    type ErrorCode = StakingExtensionErrorCode;
+    type ErrorCode = StakingExtensionError;
 
-    /// Returns `true` if the given account is a validator.
+    /// Returns `true` if the given account is an elected (active) validator.
     #[allow(clippy::wrong_self_convention)]
-    #[ink(function = 41, handle_status = false)]
+    #[ink(function = 41)]
     fn is_validator(account: AccountId) -> bool;
+
+    /// Returns the minimum stake required to become a staker.
+    #[ink(function = 42)]
+    fn min_stake() -> u128;
+
+    /// Returns the sum of the active stakes of all the stakers.
+    #[ink(function = 43)]
+    fn total_stake() -> u128;
+
+    /// Returns the number of stakers.
+    #[ink(function = 44)]
+    fn staker_count() -> u32;
+
+    /// Returns the preferences of the given validator, or `None` if the account is not a
+    /// validator.
+    #[ink(function = 45)]
+    fn validator_prefs(account: AccountId) -> Option<ValidatorPrefs>;
+
+    /// Returns `true` if the given account is a validator candidate, i.e. it may be elected at
+    /// the start of the next era.
+    #[allow(clippy::wrong_self_convention)]
+    #[ink(function = 46)]
+    fn is_validator_candidate(account: AccountId) -> bool;
+
+    /// Returns the active stake of the given account, or `None` if it is not a staker.
+    #[ink(function = 47)]
+    fn stake_of(account: AccountId) -> Option<u128>;
+
+    /// Returns the number of elected validators.
+    #[ink(function = 48)]
+    fn validator_count() -> u32;
+
+    /// Stakes `amount` of the free funds of the calling contract.
+    #[ink(function = 49)]
+    fn stake(amount: u128);
+
+    /// Increases the stake of the calling contract by `amount` of its free funds.
+    #[ink(function = 50)]
+    fn stake_more(amount: u128);
+
+    /// Returns, for each of the given accounts, whether it is an elected (active) validator.
+    #[ink(function = 51)]
+    fn are_validators(accounts: AccountBatch) -> Vec<bool>;
+
+    /// Returns the active stake of each of the given accounts (`None` for non-stakers).
+    #[ink(function = 52)]
+    fn stakes_of(accounts: AccountBatch) -> Vec<Option<u128>>;
 }
 
-/// Error codes that can be returned by the `StakingExtension`.
+/// The maximum number of accounts that can be queried by a single batch function.
+pub const MAX_BATCH_SIZE: usize = 64;
+
+/// The accounts queried by a batch function of the `StakingExtension`: at most
+/// [`MAX_BATCH_SIZE`] of them, e.g. `AccountBatch::try_from(voters)?`.
+///
+/// It is encoded just like the `Vec<AccountId>` that the runtime side expects.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[ink::scale_derive(Encode)]
+pub struct AccountBatch(Vec<AccountId>);
+
+impl TryFrom<Vec<AccountId>> for AccountBatch {
+    type Error = StakingExtensionError;
+
+    fn try_from(accounts: Vec<AccountId>) -> Result<Self, Self::Error> {
+        if accounts.len() > MAX_BATCH_SIZE {
+            return Err(StakingExtensionError::TooManyAccounts);
+        }
+        Ok(Self(accounts))
+    }
+}
+
+/// The preferences of a validator, as kept by the staking pallet.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[ink::scale_derive(Encode, Decode, TypeInfo)]
-pub struct StakingExtensionErrorCode(u32);
-impl FromStatusCode for StakingExtensionErrorCode {
-    fn from_status_code(status_code: u32) -> Result<(), Self> {
-        match status_code {
-            0 => Ok(()),
-            _ => Err(Self(status_code)),
-        }
+pub struct ValidatorPrefs {
+    /// The part of the rewards of the nominators that the validator takes, in parts per billion.
+    pub commission: u32,
+    /// Whether the validator refuses new nominations.
+    pub blocked: bool,
+}
+
+/// Chain extension that provides deterministic (and thus not secure) randomness.
+#[ink::chain_extension(extension = 1)]
+pub trait RandomnessExtension {
+    type ErrorCode = RandomnessExtensionError;
+
+    /// Returns a pseudo-random value for the given subject. The value is the same within a block,
+    /// but changes from block to block.
+    #[ink(function = 1)]
+    fn random(subject: [u8; 32]) -> [u8; 32];
+}
+
+ink::combine_extensions! {
+    /// All the chain extensions of the sandbox runtime, accessible as
+    /// `self.env().extension().staking` and `self.env().extension().randomness`.
+    pub struct SandboxExtensions {
+        /// The staking extension, registered under the ID `0`.
+        pub staking: StakingExtension,
+        /// The randomness extension, registered under the ID `1`.
+        pub randomness: RandomnessExtension,
     }
 }
 
-/// Default ink environment with `StakingExtension` included.
+/// Default ink environment with all the `SandboxExtensions` included.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[ink::scale_derive(Encode, Decode, TypeInfo)]
 pub enum StakingEnvironment {}
@@ -41,5 +133,5 @@ impl Environment for StakingEnvironment {
     type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
     type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
 
-    type ChainExtension = StakingExtension;
+    type ChainExtension = SandboxExtensions;
 }
@@ -1,3 +1,5 @@
+use crate::chain_extension::StakingExtensionError;
+
 /// Errors that can occur while interacting with the voting contract.
 #[derive(Debug, PartialEq, Eq)]
 #[ink::scale_derive(Encode, Decode, TypeInfo)]
@@ -11,4 +13,12 @@ pub enum VotingError {
     /// Voting cannot be ended (either it hasn't started yet, the deadline hasn't been reached yet,
     /// or it has already ended).
     CannotEndVoting,
+    /// The staking chain extension has failed.
+    StakingExtension(StakingExtensionError),
+}
+
+impl From<StakingExtensionError> for VotingError {
+    fn from(error: StakingExtensionError) -> Self {
+        Self::StakingExtension(error)
+    }
 }
@@ -1,6 +1,6 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
-mod chain_extension;
+pub mod chain_extension;
 pub mod errors;
 
 /// The strength of a standard vote.
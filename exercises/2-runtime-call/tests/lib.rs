@@ -1,13 +1,13 @@
 #![cfg(test)]
 
 use drink::{
-    session::{NO_ARGS, Session},
+    Sandbox, session::{NO_ARGS, NO_ENDOWMENT, Session},
 };
 use drink::sandbox_api::balance_api::BalanceAPI;
 
-use sandbox_with_staking::SandboxWithStaking;
+use sandbox_with_staking::{FakeStaking, RuntimeEvent, RuntimeOrigin, SandboxWithStaking};
 
-use crate::utils::{deploy_contract, stake_of};
+use crate::utils::{deploy_contract, free_balance_of, held_stake_of, stake_of};
 
 pub const BOB: [u8; 32] = [2; 32];
 type TestResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
@@ -82,13 +82,64 @@ fn stakes_more_if_new_funds_are_deposited(mut session: Session) -> TestResult {
     Ok(())
 }
 
+#[drink::test(sandbox = SandboxWithStaking)]
+fn staked_funds_are_held(mut session: Session) -> TestResult {
+    let contract = deploy_contract(&mut session)?;
+    let free_before = free_balance_of(&mut session, contract.clone());
+
+    session.call::<_, ()>("stake", NO_ARGS, Some(100))??;
+
+    assert_eq!(free_balance_of(&mut session, contract.clone()), free_before);
+    assert_eq!(held_stake_of(&mut session, contract), 100);
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn staking_emits_pallet_event(mut session: Session) -> TestResult {
+    let contract = deploy_contract(&mut session)?;
+
+    let staked = session
+        .call_and("stake", NO_ARGS, Some(100))?
+        .record()
+        .last_event_batch()
+        .all_events()
+        .iter()
+        .any(|record| {
+            record.event
+                == RuntimeEvent::FakeStaking(pallet_fake_staking::Event::Staked {
+                    staker: contract.clone(),
+                    amount: 100,
+                })
+        });
+
+    assert!(staked);
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn contract_reads_raised_min_stake(mut session: Session) -> TestResult {
+    deploy_contract(&mut session)?;
+    assert_eq!(session.call::<_, u128>("min_stake", NO_ARGS, NO_ENDOWMENT)??, 100);
+
+    session
+        .sandbox()
+        .execute_with(|| FakeStaking::set_min_stake(RuntimeOrigin::root(), Some(500)))
+        .unwrap();
+
+    assert_eq!(session.call::<_, u128>("min_stake", NO_ARGS, NO_ENDOWMENT)??, 500);
+    Ok(())
+}
+
 mod utils {
     use drink::{
         AccountId32,
-        Sandbox, session::{NO_ARGS, NO_ENDOWMENT, NO_SALT, Session},
+        Sandbox,
+        sandbox_api::balance_api::BalanceAPI,
+        session::{NO_ARGS, NO_ENDOWMENT, NO_SALT, Session},
     };
+    use frame_support::traits::fungible::InspectHold;
 
-    use sandbox_with_staking::{RuntimeWithStaking, SandboxWithStaking};
+    use sandbox_with_staking::{Balances, RuntimeHoldReason, RuntimeWithStaking, SandboxWithStaking};
 
     use crate::{BundleProvider, TestResult};
 
@@ -107,4 +158,15 @@ mod utils {
             .sandbox()
             .execute_with(|| pallet_fake_staking::Pallet::<RuntimeWithStaking>::stake_of(account))
     }
+
+    pub fn free_balance_of(session: &mut Session<SandboxWithStaking>, account: AccountId32) -> u128 {
+        session.sandbox().free_balance(&account)
+    }
+
+    pub fn held_stake_of(session: &mut Session<SandboxWithStaking>, account: AccountId32) -> u128 {
+        let reason = RuntimeHoldReason::FakeStaking(pallet_fake_staking::HoldReason::Staking);
+        session
+            .sandbox()
+            .execute_with(|| Balances::balance_on_hold(&reason, &account))
+    }
 }
@@ -1,15 +1,30 @@
 #![cfg(test)]
 
 use drink::{
+    AccountId32,
     Sandbox,
     sandbox_api::{balance_api::BalanceAPI, system_api::SystemAPI},
-    session::{NO_ARGS, NO_ENDOWMENT, Session},
+    session::{error::SessionError, NO_ARGS, NO_ENDOWMENT, Session},
 };
+use frame_support::{pallet_prelude::Encode, sp_runtime::DispatchError, weights::Weight, Hashable};
 
-use sandbox_with_staking::SandboxWithStaking;
-use weighted_voting::{errors::VotingError, VotingResult};
+use sandbox_with_staking::{
+    chain_extension::{
+        RecordedCalls, StakingCallRecorder, StakingExtension, StakingExtensionError,
+        MAX_BATCH_SIZE, SUCCESS,
+    },
+    FakeStaking, RuntimeWithStaking, SandboxWithStaking, System,
+};
+use weighted_voting::{
+    chain_extension::{RandomnessExtensionError, StakingExtensionError as ContractError},
+    errors::VotingError,
+    VotingResult,
+};
 
-use crate::utils::{become_validator, deploy_contract};
+use crate::utils::{
+    become_validator, deploy_contract, deploy_tester, extension_weight, random,
+    vote_against_two_others,
+};
 
 pub const BOB: [u8; 32] = [2; 32];
 pub const CHARLIE: [u8; 32] = [3; 32];
@@ -76,44 +91,400 @@ fn validator_overvotes_others(mut session: Session) -> TestResult {
 
     become_validator(&mut session, SandboxWithStaking::default_actor().into());
 
+    assert_eq!(vote_against_two_others(&mut session)?, VotingResult::For);
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn vote_queries_validator_status(mut session: Session) -> TestResult {
+    let contract = deploy_contract(&mut session)?;
+    let voter: AccountId32 = SandboxWithStaking::default_actor();
+    become_validator(&mut session, voter.clone());
+
+    let recorded = RecordedCalls::default();
     session
         .sandbox()
-        .mint_into(&BOB.into(), 1_000_000_000_000)
-        .unwrap();
+        .register_extension(StakingCallRecorder::new(recorded.clone()));
+
     session
-        .sandbox()
-        .mint_into(&CHARLIE.into(), 1_000_000_000_000)
-        .unwrap();
+        .call_and("start_voting", &["1"], NO_ENDOWMENT)?
+        .call_and("vote_for", NO_ARGS, NO_ENDOWMENT)?;
 
-    let mut session = session
+    let calls = recorded.calls();
+    let query = calls
+        .iter()
+        .find(|call| call.func_id == 41)
+        .expect("`is_validator` was not called");
+    assert_eq!(query.caller_as::<AccountId32>(), Some(contract));
+    assert_eq!(query.input_as::<AccountId32>(), Some(voter));
+    assert_eq!(query.status, SUCCESS);
+    assert_eq!(query.output_as::<bool>(), Some(true));
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn mocked_validator_overvotes_others(mut session: Session) -> TestResult {
+    deploy_contract(&mut session)?;
+
+    // The default actor is not a validator, but the extension says otherwise.
+    let mocks = session.sandbox().mock_staking_extension();
+    mocks.mock_output(41, SandboxWithStaking::default_actor(), true);
+
+    assert_eq!(vote_against_two_others(&mut session)?, VotingResult::For);
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn vote_reports_staking_extension_failure(mut session: Session) -> TestResult {
+    deploy_contract(&mut session)?;
+
+    // The staking system cannot tell whether the voter is a validator.
+    let mocks = session.sandbox().mock_staking_extension();
+    mocks.mock_error_for_all(41, StakingExtensionError::DispatchFailed);
+
+    let error = session
         .call_and("start_voting", &["1"], NO_ENDOWMENT)?
-        .call_and("vote_for", NO_ARGS, NO_ENDOWMENT)?
-        .with_actor(BOB.into())
-        .call_and("vote_against", NO_ARGS, NO_ENDOWMENT)?
-        .with_actor(CHARLIE.into())
-        .call_and("vote_against", NO_ARGS, NO_ENDOWMENT)?;
+        .call_and_expect_error::<_, VotingError>("vote_for", NO_ARGS, NO_ENDOWMENT)?;
+    assert_eq!(error, VotingError::StakingExtension(ContractError::DispatchFailed));
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn unknown_function_is_reported_to_the_contract(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+
+    // The contract does not trap: it gets the status code and decodes it.
+    let error = session.call_and_expect_error::<_, ContractError>(
+        "call_staking_function",
+        &["99"],
+        NO_ENDOWMENT,
+    )?;
+    assert_eq!(error, ContractError::UnknownFunction);
+
+    // A known function is dispatched, but fails to decode the (missing) amount to stake.
+    let error = session.call_and_expect_error::<_, ContractError>(
+        "call_staking_function",
+        &["49"],
+        NO_ENDOWMENT,
+    )?;
+    assert_eq!(error, ContractError::DecodingFailed);
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn contract_stakes_its_own_funds(mut session: Session) -> TestResult {
+    let contract = deploy_tester(&mut session)?;
+    session.sandbox().mint_into(&contract, 1_000).unwrap();
+
+    session
+        .call::<_, Result<(), ContractError>>("stake", &["300"], NO_ENDOWMENT)??
+        .expect("stake failed");
+    session
+        .call::<_, Result<(), ContractError>>("stake_more", &["100"], NO_ENDOWMENT)??
+        .expect("stake_more failed");
+
+    let stake = session
+        .sandbox()
+        .execute_with(|| FakeStaking::stake_of(contract));
+    assert_eq!(stake, Some(400));
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn staking_errors_are_reported_to_the_contract(mut session: Session) -> TestResult {
+    let contract = deploy_tester(&mut session)?;
+    session.sandbox().mint_into(&contract, 1_000).unwrap();
+
+    let mut error = |message: &str, amount: u128| {
+        session.call_and_expect_error::<_, ContractError>(
+            message,
+            &[amount.to_string()],
+            NO_ENDOWMENT,
+        )
+    };
+
+    // The minimum stake is 100.
+    assert_eq!(error("stake", 10)?, ContractError::NotEnoughStake);
+    assert_eq!(error("stake", 1_000_000)?, ContractError::InsufficientBalance);
+    assert_eq!(error("stake_more", 100)?, ContractError::NotStaker);
+
+    session
+        .call::<_, Result<(), ContractError>>("stake", &["300"], NO_ENDOWMENT)??
+        .expect("stake failed");
+    let error =
+        session.call_and_expect_error::<_, ContractError>("stake", &["300"], NO_ENDOWMENT)?;
+    assert_eq!(error, ContractError::AlreadyStaking);
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn status_codes_are_decoded_by_the_contract(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+
+    let errors = [
+        (StakingExtensionError::UnknownFunction, ContractError::UnknownFunction),
+        (StakingExtensionError::DispatchFailed, ContractError::DispatchFailed),
+        (StakingExtensionError::DecodingFailed, ContractError::DecodingFailed),
+        (StakingExtensionError::NotEnoughStake, ContractError::NotEnoughStake),
+        (StakingExtensionError::AlreadyStaking, ContractError::AlreadyStaking),
+        (StakingExtensionError::NotStaker, ContractError::NotStaker),
+        (StakingExtensionError::InsufficientBalance, ContractError::InsufficientBalance),
+        (StakingExtensionError::TooManyAccounts, ContractError::TooManyAccounts),
+    ];
+
+    let mocks = session.sandbox().mock_staking_extension();
+    for (runtime_error, contract_error) in errors {
+        mocks.mock_error_for_all(42, runtime_error);
+
+        let error = session.call_and_expect_error::<_, ContractError>(
+            "read_min_stake",
+            &["1"],
+            NO_ENDOWMENT,
+        )?;
+        assert_eq!(error, contract_error);
+    }
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn extension_weight_is_included_in_the_gas(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+    let min_stake_weight = extension_weight(42, 0);
+
+    let mut gas_consumed = |times: u32| -> TestResult<Weight> {
+        session
+            .call::<_, Result<u128, ContractError>>(
+                "read_min_stake",
+                &[times.to_string()],
+                NO_ENDOWMENT,
+            )??
+            .expect("min_stake failed");
+        Ok(session.record().last_call_result().gas_consumed)
+    };
+    let without_extension = gas_consumed(0)?;
+    let with_extension = gas_consumed(10)?;
+
+    let charged = min_stake_weight.saturating_mul(10);
+    assert!(with_extension.ref_time() >= without_extension.ref_time() + charged.ref_time());
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn too_low_gas_limit_runs_out_of_gas(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+    let min_stake_weight = extension_weight(42, 0);
+
+    session
+        .call::<_, Result<u128, ContractError>>("read_min_stake", &["0"], NO_ENDOWMENT)??
+        .expect("min_stake failed");
+    let required = session.record().last_call_result().gas_required;
+
+    // Enough for the message itself, but only for half of the extension calls.
+    session.set_gas_limit(required.saturating_add(min_stake_weight.saturating_mul(5)));
+    let result =
+        session.call::<_, Result<u128, ContractError>>("read_min_stake", &["10"], NO_ENDOWMENT);
+
+    let out_of_gas = DispatchError::from(pallet_contracts::Error::<RuntimeWithStaking>::OutOfGas);
+    assert!(matches!(result, Err(SessionError::CallFailed(error)) if error == out_of_gas));
+
+    Ok(())
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn random_value_changes_only_across_blocks(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+    let subject = [7; 32];
+
+    let value = random(&mut session, subject)?;
+    assert_eq!(random(&mut session, subject)?, value);
+    assert_ne!(random(&mut session, [8; 32])?, value);
+
+    // The call is answered by the randomness extension (ID `1`), not by the staking one.
+    let expected = session.sandbox().execute_with(|| {
+        (System::parent_hash(), System::block_number(), subject).blake2_256()
+    });
+    assert_eq!(value, expected);
 
     session.sandbox().build_block();
+    assert_ne!(random(&mut session, subject)?, value);
 
-    let result = session
-        .with_actor(SandboxWithStaking::default_actor())
-        .call::<_, Result<VotingResult, VotingError>>("end_voting", NO_ARGS, NO_ENDOWMENT)??
-        .expect("end_voting failed");
+    Ok(())
+}
 
-    assert_eq!(result, VotingResult::For);
+#[drink::test(sandbox = SandboxWithStaking)]
+fn unknown_randomness_function_is_reported_to_the_contract(mut session: Session) -> TestResult {
+    deploy_tester(&mut session)?;
+
+    let mut error = |func_id: u16| {
+        session.call_and_expect_error::<_, RandomnessExtensionError>(
+            "call_randomness_function",
+            &[func_id.to_string()],
+            NO_ENDOWMENT,
+        )
+    };
+
+    // `random` is the only function of the extension.
+    assert_eq!(error(2)?, RandomnessExtensionError::UnknownFunction);
+    // `random` itself is dispatched, but fails to decode the (missing) subject.
+    assert_eq!(error(1)?, RandomnessExtensionError::DecodingFailed);
+
+    Ok(())
+}
+
+#[test]
+fn batch_queries_are_charged_per_account() {
+    let input_len = |accounts: usize| vec![AccountId32::from(BOB); accounts].encode().len() as u32;
+    let batch_weight = |func_id, accounts| extension_weight(func_id, input_len(accounts));
+    let single_weight =
+        |func_id| extension_weight(func_id, AccountId32::from(BOB).encode().len() as u32);
+
+    for (batch, single) in [(51, 41), (52, 47)] {
+        // A batch of one account costs as much as the corresponding single query (up to the
+        // length prefix of the input).
+        assert!(batch_weight(batch, 1).ref_time() >= single_weight(single).ref_time());
+        // Every additional account is charged.
+        assert!(batch_weight(batch, 10).ref_time() > batch_weight(batch, 9).ref_time());
+        let ten_single_queries = single_weight(single).ref_time().saturating_mul(10);
+        assert!(batch_weight(batch, 10).ref_time() >= ten_single_queries);
+    }
+}
+
+#[drink::test(sandbox = SandboxWithStaking)]
+fn batch_queries_answer_for_every_account(mut session: Session) -> TestResult {
+    let validator = SandboxWithStaking::default_actor();
+    become_validator(&mut session, validator.clone());
+
+    let dispatch = |func_id: u16, accounts: Vec<AccountId32>| {
+        StakingExtension::<FakeStaking>::dispatch(func_id, &accounts.encode(), CHARLIE.into())
+    };
+    let accounts = vec![validator.clone(), BOB.into(), validator.clone()];
+    let too_many = vec![AccountId32::from(BOB); MAX_BATCH_SIZE as usize + 1];
+
+    session.sandbox().execute_with(|| {
+        let stake = FakeStaking::stake_of(validator);
+        assert!(stake.is_some());
+
+        assert_eq!(dispatch(51, accounts.clone()), Ok(vec![true, false, true].encode()));
+        assert_eq!(dispatch(52, accounts), Ok(vec![stake, None, stake].encode()));
+
+        assert_eq!(dispatch(51, too_many.clone()), Err(StakingExtensionError::TooManyAccounts));
+        assert_eq!(dispatch(52, too_many), Err(StakingExtensionError::TooManyAccounts));
+    });
 
     Ok(())
 }
 
+#[test]
+fn extension_works_with_any_staking_system() {
+    use frame_support::pallet_prelude::DispatchResult;
+    use pallet_fake_staking::{StakingError, StakingInterface};
+    use weighted_voting::chain_extension::ValidatorPrefs;
+
+    const PREFS: ValidatorPrefs = ValidatorPrefs {
+        commission: 100,
+        blocked: true,
+    };
+
+    /// A staking system, unrelated to the staking pallet, in which Bob is the only staker and
+    /// validator, and nobody can stake. Only the errors of `stake_more` are recognized.
+    struct MockStaking;
+
+    impl StakingInterface for MockStaking {
+        type AccountId = AccountId32;
+        type Balance = u128;
+        // The preferences are not the ones of the pallet, but the contract-side ones.
+        type ValidatorPrefs = ValidatorPrefs;
+
+        fn minimum_stake() -> u128 {
+            7
+        }
+        fn stake(_: &AccountId32, _: u128) -> DispatchResult {
+            Err(DispatchError::Other("Staking is disabled"))
+        }
+        fn stake_more(_: &AccountId32, _: u128) -> DispatchResult {
+            Err(DispatchError::Other("Not a staker"))
+        }
+        fn stake_weight() -> Weight {
+            Weight::zero()
+        }
+        fn stake_more_weight() -> Weight {
+            Weight::zero()
+        }
+        fn staking_error(error: &DispatchError) -> Option<StakingError> {
+            (*error == DispatchError::Other("Not a staker")).then_some(StakingError::NotStaker)
+        }
+        fn active_stake(who: &AccountId32) -> Option<u128> {
+            (who == &AccountId32::from(BOB)).then_some(1_000)
+        }
+        fn is_validator(who: &AccountId32) -> bool {
+            who == &AccountId32::from(BOB)
+        }
+        fn is_validator_candidate(who: &AccountId32) -> bool {
+            Self::is_validator(who)
+        }
+        fn validators() -> Vec<AccountId32> {
+            vec![BOB.into()]
+        }
+        fn validator_count() -> u32 {
+            1
+        }
+        fn validator_prefs(who: &AccountId32) -> Option<ValidatorPrefs> {
+            Self::is_validator(who).then_some(PREFS)
+        }
+        fn total_stake() -> u128 {
+            1_000
+        }
+        fn staker_count() -> u32 {
+            1
+        }
+    }
+
+    let dispatch = |func_id, input: Vec<u8>| {
+        StakingExtension::<MockStaking>::dispatch(func_id, &input, CHARLIE.into())
+    };
+    let bob = AccountId32::from(BOB);
+    let charlie = AccountId32::from(CHARLIE);
+
+    assert_eq!(dispatch(41, bob.encode()), Ok(true.encode()));
+    assert_eq!(dispatch(41, charlie.encode()), Ok(false.encode()));
+    assert_eq!(dispatch(42, Vec::new()), Ok(7u128.encode()));
+    assert_eq!(dispatch(45, bob.encode()), Ok(Some(PREFS).encode()));
+    assert_eq!(dispatch(45, charlie.encode()), Ok(None::<ValidatorPrefs>.encode()));
+    assert_eq!(
+        dispatch(52, vec![bob, charlie].encode()),
+        Ok(vec![Some(1_000u128), None].encode())
+    );
+    assert_eq!(dispatch(49, 100u128.encode()), Err(StakingExtensionError::DispatchFailed));
+    assert_eq!(dispatch(50, 100u128.encode()), Err(StakingExtensionError::NotStaker));
+}
+
 mod utils {
     use drink::{
         AccountId32,
-        Sandbox, session::{NO_ENDOWMENT, NO_SALT, Session},
+        Sandbox,
+        sandbox_api::{balance_api::BalanceAPI, system_api::SystemAPI},
+        session::{NO_ARGS, NO_ENDOWMENT, NO_SALT, Session},
     };
 
-    use sandbox_with_staking::{RuntimeOrigin, RuntimeWithStaking, SandboxWithStaking};
+    use frame_support::weights::Weight;
+    use sandbox_with_staking::{
+        chain_extension::weight_of, FakeStaking, RuntimeOrigin, RuntimeWithStaking,
+        SandboxWithStaking,
+    };
+    use weighted_voting::{
+        chain_extension::RandomnessExtensionError, errors::VotingError, VotingResult,
+    };
 
-    use crate::{BundleProvider, TestResult};
+    use crate::{BundleProvider, TestResult, BOB, CHARLIE};
 
     pub fn deploy_contract(session: &mut Session<SandboxWithStaking>) -> TestResult<AccountId32> {
         Ok(session.deploy_bundle(
@@ -125,12 +496,76 @@ mod utils {
         )?)
     }
 
+    /// Runs a voting in which the default actor votes for and `BOB` and `CHARLIE` vote against,
+    /// and returns its result.
+    pub fn vote_against_two_others(
+        session: &mut Session<SandboxWithStaking>,
+    ) -> TestResult<VotingResult> {
+        session
+            .sandbox()
+            .mint_into(&BOB.into(), 1_000_000_000_000)
+            .unwrap();
+        session
+            .sandbox()
+            .mint_into(&CHARLIE.into(), 1_000_000_000_000)
+            .unwrap();
+
+        session
+            .call_and("start_voting", &["1"], NO_ENDOWMENT)?
+            .call_and("vote_for", NO_ARGS, NO_ENDOWMENT)?
+            .with_actor(BOB.into())
+            .call_and("vote_against", NO_ARGS, NO_ENDOWMENT)?
+            .with_actor(CHARLIE.into())
+            .call_and("vote_against", NO_ARGS, NO_ENDOWMENT)?;
+
+        session.sandbox().build_block();
+
+        let result = session
+            .with_actor(SandboxWithStaking::default_actor())
+            .call::<_, Result<VotingResult, VotingError>>("end_voting", NO_ARGS, NO_ENDOWMENT)??
+            .expect("end_voting failed");
+        Ok(result)
+    }
+
+    pub fn deploy_tester(session: &mut Session<SandboxWithStaking>) -> TestResult<AccountId32> {
+        Ok(session.deploy_bundle(
+            BundleProvider::ExtensionTester.bundle()?,
+            "new",
+            NO_ARGS,
+            NO_SALT,
+            NO_ENDOWMENT,
+        )?)
+    }
+
+    /// The random value for `subject` that the randomness extension gives to the tester contract.
+    pub fn random(
+        session: &mut Session<SandboxWithStaking>,
+        subject: [u8; 32],
+    ) -> TestResult<[u8; 32]> {
+        let random = session.call::<_, Result<[u8; 32], RandomnessExtensionError>>(
+            "random",
+            &[format!("{subject:?}")],
+            NO_ENDOWMENT,
+        )??;
+        Ok(random.expect("random failed"))
+    }
+
+    /// The weight charged by the staking extension of the sandbox for the known function `func_id`.
+    pub fn extension_weight(func_id: u16, input_len: u32) -> Weight {
+        weight_of::<RuntimeWithStaking, FakeStaking>(func_id, input_len).expect("Known function")
+    }
+
     pub fn become_validator(session: &mut Session<SandboxWithStaking>, account: AccountId32) {
         session.sandbox().execute_with(|| {
             pallet_fake_staking::Pallet::<RuntimeWithStaking>::become_validator(
                 RuntimeOrigin::signed(account),
             )
             .unwrap();
+            // Candidates are elected at the start of every era. Do not wait for it.
+            pallet_fake_staking::Pallet::<RuntimeWithStaking>::force_election(
+                RuntimeOrigin::root(),
+            )
+            .unwrap();
         });
     }
 }
@@ -0,0 +1,300 @@
+//! Benchmarks for the `FakeStaking` pallet.
+
+use frame_benchmarking::v2::*;
+use frame_support::{
+    pallet_prelude::*,
+    sp_runtime::{traits::Zero, Perbill},
+    traits::fungible::{Inspect, Mutate, MutateHold},
+};
+use frame_system::{pallet_prelude::BlockNumberFor, RawOrigin};
+use sp_std::{vec, vec::Vec};
+
+use crate::*;
+
+const SEED: u32 = 0;
+
+/// The stake used by the benchmarked stakers.
+fn bench_stake<T: Config>() -> u128 {
+    Pallet::<T>::min_stake().saturating_mul(10)
+}
+
+/// Create an account with enough funds to stake `bench_stake` many times.
+fn funded_account<T: Config>(name: &'static str, index: u32) -> T::AccountId {
+    let who = account(name, index, SEED);
+    T::Currency::set_balance(&who, bench_stake::<T>().saturating_mul(100).saturating_add(1_000));
+    who
+}
+
+/// Create an account that stakes `bench_stake`.
+fn staker<T: Config>(name: &'static str, index: u32) -> T::AccountId {
+    let who = funded_account::<T>(name, index);
+    Pallet::<T>::stake(RawOrigin::Signed(who.clone()).into(), bench_stake::<T>())
+        .expect("Failed to stake");
+    who
+}
+
+/// Create `n` validators.
+fn validators<T: Config>(n: u32) -> Vec<T::AccountId> {
+    (0..n)
+        .map(|i| {
+            let validator = account("validator", i, SEED);
+            Pallet::<T>::force_add_validator(RawOrigin::Root.into(), validator.clone())
+                .expect("Failed to add validator");
+            validator
+        })
+        .collect()
+}
+
+/// Create an account that stakes `bench_stake` and nominates `n` validators.
+fn nominator<T: Config>(name: &'static str, index: u32, n: u32) -> T::AccountId {
+    let who = staker::<T>(name, index);
+    if n > 0 {
+        let targets = validators::<T>(n);
+        Pallet::<T>::nominate(RawOrigin::Signed(who.clone()).into(), targets)
+            .expect("Failed to nominate");
+    }
+    who
+}
+
+/// Create `m` stakers nominating `validator`.
+fn nominators_of<T: Config>(validator: &T::AccountId, m: u32) {
+    for i in 0..m {
+        let who = staker::<T>("nominator", i);
+        Pallet::<T>::nominate(RawOrigin::Signed(who).into(), vec![validator.clone()])
+            .expect("Failed to nominate");
+    }
+}
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn become_validator() {
+        let caller = staker::<T>("caller", 0);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()));
+
+        assert!(Pallet::<T>::is_validator_candidate(caller));
+    }
+
+    #[benchmark]
+    fn stake() {
+        let caller = funded_account::<T>("caller", 0);
+        let amount = bench_stake::<T>();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), amount);
+
+        assert_eq!(Pallet::<T>::stake_of(caller), Some(amount));
+    }
+
+    #[benchmark]
+    fn stake_more(n: Linear<0, { T::MaxNominations::get() }>) {
+        let caller = nominator::<T>("caller", 0, n);
+        let amount = bench_stake::<T>();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), amount);
+
+        assert_eq!(Pallet::<T>::stake_of(caller), Some(amount.saturating_mul(2)));
+    }
+
+    #[benchmark]
+    fn unbond(n: Linear<0, { T::MaxNominations::get() }>) {
+        let caller = nominator::<T>("caller", 0, n);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), bench_stake::<T>());
+
+        assert_eq!(Pallet::<T>::stake_of(caller), None);
+    }
+
+    #[benchmark]
+    fn withdraw_unbonded(
+        u: Linear<1, { T::MaxUnlockingChunks::get() }>,
+    ) -> Result<(), BenchmarkError> {
+        let caller = funded_account::<T>("caller", 0);
+        let value = bench_stake::<T>();
+        let chunks = (0..u)
+            .map(|_| UnlockChunk {
+                value,
+                unlock_at: BlockNumberFor::<T>::zero(),
+            })
+            .collect::<Vec<_>>();
+        T::Currency::hold(
+            &HoldReason::Staking.into(),
+            &caller,
+            value.saturating_mul(u.into()),
+        )?;
+        Stakers::<T>::insert(
+            &caller,
+            StakingLedger::<T> {
+                unlocking: UnlockChunksOf::<T>::try_from(chunks).expect("`u` is within the bound"),
+                ..Default::default()
+            },
+        );
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()));
+
+        assert!(!Stakers::<T>::contains_key(&caller));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn stop_validating(m: Linear<0, { T::MaxNominators::get() }>) {
+        let caller = validators::<T>(1).remove(0);
+        nominators_of::<T>(&caller, m);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()));
+
+        assert!(!Pallet::<T>::is_validator_candidate(caller));
+    }
+
+    #[benchmark]
+    fn chill(
+        n: Linear<0, { T::MaxNominations::get() }>,
+        m: Linear<0, { T::MaxNominators::get() - 1 }>,
+    ) -> Result<(), BenchmarkError> {
+        let caller = nominator::<T>("caller", 0, n);
+        Pallet::<T>::force_add_validator(RawOrigin::Root.into(), caller.clone())?;
+        nominators_of::<T>(&caller, m);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()));
+
+        assert!(!Pallet::<T>::is_validator_candidate(caller.clone()));
+        assert!(!Nominations::<T>::contains_key(&caller));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn force_add_validator() {
+        let validator: T::AccountId = account("validator", 0, SEED);
+
+        #[extrinsic_call]
+        _(RawOrigin::Root, validator.clone());
+
+        assert!(Pallet::<T>::is_validator_candidate(validator));
+    }
+
+    #[benchmark]
+    fn force_remove_validator(m: Linear<0, { T::MaxNominators::get() }>) {
+        let validator = validators::<T>(1).remove(0);
+        nominators_of::<T>(&validator, m);
+
+        #[extrinsic_call]
+        _(RawOrigin::Root, validator.clone());
+
+        assert!(!Pallet::<T>::is_validator_candidate(validator));
+        assert_eq!(Nominations::<T>::count(), 0);
+    }
+
+    #[benchmark]
+    fn nominate(n: Linear<1, { T::MaxNominations::get() }>) {
+        let caller = staker::<T>("caller", 0);
+        let targets = validators::<T>(n);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), targets);
+
+        assert_eq!(Nominations::<T>::get(&caller).map(|t| t.len() as u32), Some(n));
+    }
+
+    #[benchmark]
+    fn slash(n: Linear<0, { T::MaxNominators::get() }>) -> Result<(), BenchmarkError> {
+        let validator = staker::<T>("validator", 0);
+        Pallet::<T>::force_add_validator(RawOrigin::Root.into(), validator.clone())?;
+        for i in 0..n {
+            let nominator = staker::<T>("nominator", i);
+            Pallet::<T>::nominate(RawOrigin::Signed(nominator).into(), vec![validator.clone()])?;
+        }
+
+        #[extrinsic_call]
+        _(RawOrigin::Root, validator.clone(), Perbill::from_percent(10));
+
+        assert!(Pallet::<T>::stake_of(validator).unwrap_or_default() < bench_stake::<T>());
+        Ok(())
+    }
+
+    #[benchmark]
+    fn set_min_stake() {
+        let min_stake = Pallet::<T>::min_stake().saturating_add(1);
+
+        #[extrinsic_call]
+        _(RawOrigin::Root, Some(min_stake));
+
+        assert_eq!(Pallet::<T>::min_stake(), min_stake);
+    }
+
+    #[benchmark]
+    fn new_era(s: Linear<0, { T::MaxStakers::get() }>) {
+        for i in 0..s {
+            staker::<T>("staker", i);
+        }
+        let era = CurrentEra::<T>::get();
+
+        #[block]
+        {
+            Pallet::<T>::new_era();
+        }
+
+        assert_eq!(CurrentEra::<T>::get(), era.saturating_add(1));
+    }
+
+    #[benchmark]
+    fn bond_for() {
+        let caller = funded_account::<T>("caller", 0);
+        let beneficiary: T::AccountId = account("beneficiary", 0, SEED);
+        // The stash must exist to hold the stake.
+        T::Currency::set_balance(&beneficiary, T::Currency::minimum_balance());
+        let amount = bench_stake::<T>();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), beneficiary.clone(), amount);
+
+        assert_eq!(Pallet::<T>::stake_of(beneficiary.clone()), Some(amount));
+        assert_eq!(Pallet::<T>::controller_of(beneficiary), Some(caller));
+    }
+
+    #[benchmark]
+    fn set_controller() {
+        let caller = staker::<T>("caller", 0);
+        let controller: T::AccountId = account("controller", 0, SEED);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), Some(controller.clone()));
+
+        assert_eq!(Pallet::<T>::controller_of(caller), Some(controller));
+    }
+
+    #[benchmark]
+    fn validate() {
+        let caller = staker::<T>("caller", 0);
+        let prefs = ValidatorPrefs {
+            commission: Perbill::from_percent(10),
+            blocked: true,
+        };
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), prefs);
+
+        assert_eq!(Pallet::<T>::validator_prefs(caller), Some(prefs));
+    }
+
+    #[benchmark]
+    fn force_election(v: Linear<0, { T::MaxValidators::get() }>) {
+        validators::<T>(v);
+
+        #[extrinsic_call]
+        _(RawOrigin::Root);
+
+        let seats = T::ValidatorCount::get().min(v);
+        assert_eq!(Pallet::<T>::validator_count(), seats);
+    }
+
+    impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
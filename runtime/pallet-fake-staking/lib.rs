@@ -1,28 +1,171 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![recursion_limit = "256"]
-#![allow(deprecated)] // for constant weights
 
 use frame_support::pallet_prelude::StorageVersion;
 
 pub use pallet::*;
+pub use traits::{StakingError, StakingInterface};
+pub use weights::WeightInfo;
 
-const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod migrations;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+pub mod traits;
+pub mod weights;
 
-/// The minimum stake required to become a staker.
-pub const THRESHOLD: u128 = 100;
+/// Counter for the number of eras that have passed.
+pub type EraIndex = u32;
+
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
 
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::{
         pallet_prelude::{*, DispatchResult},
+        sp_runtime::{traits::Zero, Perbill, Perquintill},
+        traits::{
+            fungible::{Inspect, InspectHold, Mutate, MutateHold},
+            tokens::{Fortitude, Precision, Preservation},
+        },
         Twox64Concat,
     };
-    use frame_system::{ensure_signed, pallet_prelude::OriginFor};
+    use frame_system::{
+        ensure_root, ensure_signed,
+        pallet_prelude::{BlockNumberFor, OriginFor},
+    };
+    use sp_std::vec::Vec;
+    #[cfg(any(feature = "try-runtime", test))]
+    use frame_support::sp_runtime::TryRuntimeError;
+    #[cfg(any(feature = "try-runtime", test))]
+    use sp_std::collections::btree_map::BTreeMap;
 
     use super::*;
 
     #[pallet::config]
-    pub trait Config: frame_system::Config {}
+    pub trait Config: frame_system::Config {
+        /// The overarching event type.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// The currency in which the stakes are held and the rewards are minted.
+        type Currency: MutateHold<
+                Self::AccountId,
+                Reason = Self::RuntimeHoldReason,
+                Balance = u128,
+            > + Mutate<Self::AccountId>;
+
+        /// The overarching hold reason.
+        type RuntimeHoldReason: From<HoldReason>;
+
+        /// The default minimum stake required to become a staker. It can be overridden with
+        /// `set_min_stake`.
+        #[pallet::constant]
+        type MinStake: Get<u128>;
+
+        /// The number of blocks that the unbonded funds stay on hold before they can be withdrawn.
+        #[pallet::constant]
+        type BondingDuration: Get<BlockNumberFor<Self>>;
+
+        /// The maximum number of unlocking chunks that an account can have at the same time.
+        #[pallet::constant]
+        type MaxUnlockingChunks: Get<u32>;
+
+        /// The maximum number of validators.
+        #[pallet::constant]
+        type MaxValidators: Get<u32>;
+
+        /// The minimum stake that an account must have to become (and stay) a validator. `None`
+        /// means that validators are not required to stake at all.
+        #[pallet::constant]
+        type MinValidatorStake: Get<Option<u128>>;
+
+        /// The maximum number of validators that a staker can nominate.
+        #[pallet::constant]
+        type MaxNominations: Get<u32>;
+
+        /// The maximum number of stakers that can nominate at the same time. It bounds the work
+        /// done when a validator is removed or slashed.
+        #[pallet::constant]
+        type MaxNominators: Get<u32>;
+
+        /// The number of blocks in an era.
+        #[pallet::constant]
+        type EraDuration: Get<BlockNumberFor<Self>>;
+
+        /// The amount minted at the start of every era and distributed among the stakers
+        /// proportionally to their stakes.
+        #[pallet::constant]
+        type RewardPerEra: Get<u128>;
+
+        /// The maximum number of stakers. It bounds the work done at the start of every era, when
+        /// the rewards are paid out.
+        #[pallet::constant]
+        type MaxStakers: Get<u32>;
+
+        /// The number of validators elected at the start of every era. It is capped by
+        /// `MaxValidators`.
+        #[pallet::constant]
+        type ValidatorCount: Get<u32>;
+
+        /// Weight information for the calls of this pallet.
+        type WeightInfo: WeightInfo;
+    }
+
+    /// A reason for the pallet placing a hold on funds.
+    #[pallet::composite_enum]
+    pub enum HoldReason {
+        /// The funds are held as a stake.
+        #[codec(index = 0)]
+        Staking,
+    }
+
+    /// Events emitted by the pallet.
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// An account has become a validator.
+        BecameValidator { validator: T::AccountId },
+        /// An account has stopped being a validator.
+        StoppedValidating { validator: T::AccountId },
+        /// An account has started staking.
+        Staked { staker: T::AccountId, amount: u128 },
+        /// A staker has increased their stake.
+        StakedMore { staker: T::AccountId, amount: u128 },
+        /// A staker has unbonded a part of their stake.
+        Unbonded { staker: T::AccountId, amount: u128 },
+        /// An account has withdrawn its unbonded funds.
+        Withdrawn { staker: T::AccountId, amount: u128 },
+        /// A staker has nominated validators.
+        Nominated {
+            nominator: T::AccountId,
+            targets: Vec<T::AccountId>,
+        },
+        /// A staker has stopped nominating.
+        StoppedNominating { nominator: T::AccountId },
+        /// The minimum stake has been changed.
+        MinStakeSet { min_stake: u128 },
+        /// A new era has started.
+        NewEra { era: EraIndex },
+        /// The active validators have been elected.
+        ValidatorsElected { validators: Vec<T::AccountId> },
+        /// A staker has been rewarded for the past era.
+        Rewarded { staker: T::AccountId, amount: u128 },
+        /// A staker has been slashed.
+        Slashed { staker: T::AccountId, amount: u128 },
+        /// A validator has set its preferences.
+        ValidatorPrefsSet {
+            validator: T::AccountId,
+            prefs: ValidatorPrefs,
+        },
+        /// The controller of a stash has been set (or cleared).
+        ControllerSet {
+            stash: T::AccountId,
+            controller: Option<T::AccountId>,
+        },
+    }
 
     /// Errors that can occur during the execution of the pallet.
     #[pallet::error]
@@ -34,72 +177,1053 @@ pub mod pallet {
         NotEnoughStake,
         /// The account is not a staker. Cannot call `stake_more` without calling `stake` first.
         NotStaker,
+        /// The free balance of the account is not enough to cover the stake.
+        InsufficientBalance,
+        /// The stake left after unbonding would be below the minimum stake. Either unbond less or
+        /// unbond everything.
+        InsufficientRemainingStake,
+        /// The account has too many unlocking chunks. Call `withdraw_unbonded` first.
+        NoMoreChunks,
+        /// The validator set is full.
+        TooManyValidators,
+        /// The account is not a validator.
+        NotValidator,
+        /// The stake of the account is not enough to be a validator.
+        NotEnoughSelfStake,
+        /// No validators were given to nominate.
+        EmptyTargets,
+        /// Too many validators were given to nominate.
+        TooManyTargets,
+        /// There are already `MaxNominators` nominators.
+        TooManyNominators,
+        /// There are already `MaxStakers` stakers.
+        TooManyStakers,
+        /// The caller is neither the stash nor its controller.
+        NotController,
+        /// The stash already has a ledger, so only the stash itself can bond for it.
+        AlreadyBonded,
+        /// The stash cannot hold any funds, because it does not exist. Others can only bond for
+        /// stashes that own at least the existential deposit.
+        UnfundedStash,
+        /// The validator does not accept new nominations.
+        ValidatorBlocked,
+    }
+
+    /// The preferences of a validator.
+    #[derive(
+        Clone,
+        Copy,
+        Default,
+        Encode,
+        Decode,
+        Eq,
+        PartialEq,
+        RuntimeDebug,
+        TypeInfo,
+        MaxEncodedLen,
+    )]
+    pub struct ValidatorPrefs {
+        /// The part of the rewards of its nominators that the validator takes for itself.
+        pub commission: Perbill,
+        /// Whether the validator refuses new nominations.
+        pub blocked: bool,
+    }
+
+    /// A part of the stake that is being unbonded.
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct UnlockChunk<BlockNumber> {
+        /// The unbonded amount.
+        pub value: u128,
+        /// The block number from which the amount can be withdrawn.
+        pub unlock_at: BlockNumber,
+    }
+
+    /// The unlocking chunks of an account.
+    pub type UnlockChunksOf<T> =
+        BoundedVec<UnlockChunk<BlockNumberFor<T>>, <T as Config>::MaxUnlockingChunks>;
+
+    /// The staking state of an account.
+    #[derive(
+        CloneNoBound,
+        DefaultNoBound,
+        Encode,
+        Decode,
+        EqNoBound,
+        PartialEqNoBound,
+        RuntimeDebugNoBound,
+        TypeInfo,
+        MaxEncodedLen,
+    )]
+    #[scale_info(skip_type_params(T))]
+    #[codec(mel_bound())]
+    pub struct StakingLedger<T: Config> {
+        /// The stake that is actively staked. Zero if the account has unbonded everything, but
+        /// still has some funds to withdraw.
+        pub active: u128,
+        /// The parts of the stake that are being unbonded.
+        pub unlocking: UnlockChunksOf<T>,
+        /// The last era for which the account has been rewarded.
+        pub last_reward_era: Option<EraIndex>,
     }
 
-    /// The set of validators.
+    impl<T: Config> StakingLedger<T> {
+        /// Whether the ledger holds any funds at all.
+        pub fn is_empty(&self) -> bool {
+            self.active == 0 && self.unlocking.is_empty()
+        }
+    }
+
+    /// The set of validators together with their preferences. Its size is bounded by
+    /// `Config::MaxValidators`.
+    #[pallet::storage]
+    pub type Validators<T: Config> =
+        CountedStorageMap<_, Twox64Concat, T::AccountId, ValidatorPrefs>;
+
+    /// The validators elected for the current era, i.e. the validator candidates with the highest
+    /// self stake plus backing.
+    #[pallet::storage]
+    pub type ActiveValidators<T: Config> =
+        StorageValue<_, BoundedVec<T::AccountId, T::MaxValidators>, ValueQuery>;
+
+    /// The ledgers of the stakers. An entry is kept as long as the account has some active stake
+    /// or some funds being unbonded. Its size is bounded by `Config::MaxStakers`.
     #[pallet::storage]
-    pub type Validators<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, ()>;
+    pub type Stakers<T: Config> =
+        CountedStorageMap<_, Twox64Concat, T::AccountId, StakingLedger<T>>;
+
+    /// The sum of the active stakes of all the stakers.
+    #[pallet::storage]
+    pub type TotalStake<T: Config> = StorageValue<_, u128, ValueQuery>;
+
+    /// The controllers of the stashes. A controller may manage the stake of the stash (besides
+    /// the stash itself). The entry is removed together with the ledger of the stash.
+    #[pallet::storage]
+    pub type Controllers<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, T::AccountId>;
+
+    /// The validators nominated by the stakers.
+    #[pallet::storage]
+    pub type Nominations<T: Config> = CountedStorageMap<
+        _,
+        Twox64Concat,
+        T::AccountId,
+        BoundedVec<T::AccountId, T::MaxNominations>,
+    >;
 
-    /// The stakes of the stakers.
+    /// The total stake of the nominators backing a validator. The whole stake of a nominator backs
+    /// every validator that it nominates.
     #[pallet::storage]
-    pub type Stakers<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u128>;
+    pub type Backing<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u128, ValueQuery>;
+
+    /// The index of the current era.
+    #[pallet::storage]
+    pub type CurrentEra<T: Config> = StorageValue<_, EraIndex, ValueQuery>;
+
+    /// The minimum stake set by the root. If not set, `Config::MinStake` is used.
+    #[pallet::storage]
+    pub type MinStakeOverride<T: Config> = StorageValue<_, u128>;
 
     #[pallet::pallet]
     #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let era_duration = T::EraDuration::get();
+            if era_duration.is_zero() || !(now % era_duration).is_zero() {
+                return Weight::zero();
+            }
+            Self::new_era()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_: BlockNumberFor<T>) -> Result<(), TryRuntimeError> {
+            Self::do_try_state()
+        }
+    }
+
+    /// The initial validators and stakers.
+    #[pallet::genesis_config]
+    #[derive(DefaultNoBound)]
+    pub struct GenesisConfig<T: Config> {
+        /// The accounts that are validators from the start.
+        pub validators: Vec<T::AccountId>,
+        /// The accounts that are stakers from the start, together with their stakes. The stakes
+        /// are put on hold, so the accounts must be endowed with enough funds.
+        pub stakers: Vec<(T::AccountId, u128)>,
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+        fn build(&self) {
+            assert!(
+                self.stakers.len() <= T::MaxStakers::get() as usize,
+                "Too many genesis stakers"
+            );
+            for (staker, stake) in &self.stakers {
+                assert!(
+                    *stake >= Pallet::<T>::min_stake(),
+                    "Genesis stake is below the minimum stake"
+                );
+                assert!(
+                    Pallet::<T>::active_stake(staker).is_none(),
+                    "Staker is duplicated in the genesis"
+                );
+                Pallet::<T>::hold_stake(staker, staker, *stake)
+                    .expect("Genesis staker cannot afford their stake");
+                Pallet::<T>::set_stake(staker, *stake);
+            }
+
+            for validator in &self.validators {
+                Validators::<T>::insert(validator, ValidatorPrefs::default());
+            }
+            assert!(
+                Validators::<T>::count() <= T::MaxValidators::get(),
+                "Too many genesis validators"
+            );
+            Pallet::<T>::elect();
+        }
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        /// Become a validator.
+        /// Become a validator with the default preferences (no commission, open for
+        /// nominations). If `MinValidatorStake` is set, the caller must stake at least that much.
         #[pallet::call_index(0)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::become_validator())]
         pub fn become_validator(origin: OriginFor<T>) -> DispatchResult {
             let validator = ensure_signed(origin)?;
-            Validators::<T>::insert(&validator, ());
-            Ok(())
+            Self::ensure_validator_stake(&validator)?;
+            Self::add_validator(validator, ValidatorPrefs::default())
         }
 
-        /// Stake some amount. The stake must be at least `THRESHOLD`.
+        /// Stake some amount. The stake must be at least the minimum stake. The staked amount is
+        /// put on hold.
         #[pallet::call_index(1)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::stake())]
         pub fn stake(origin: OriginFor<T>, stake: u128) -> DispatchResult {
             let staker = ensure_signed(origin)?;
-            ensure!(stake >= THRESHOLD, Error::<T>::NotEnoughStake);
-            Stakers::<T>::mutate(staker, |s| match s {
-                Some(_) => Err(Error::<T>::AlreadyStaking),
-                None => {
-                    *s = Some(stake);
-                    Ok(())
-                }
-            })?;
-            Ok(())
+            Self::do_stake(&staker, staker.clone(), stake)
         }
 
-        /// Stake more amount.
+        /// Stake more amount. The additional amount is put on hold.
         #[pallet::call_index(2)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::stake_more(T::MaxNominations::get()))]
         pub fn stake_more(origin: OriginFor<T>, more: u128) -> DispatchResult {
             let staker = ensure_signed(origin)?;
-            Stakers::<T>::mutate(staker, |s| match s {
-                Some(stake) => {
-                    *s = Some(stake.saturating_add(more));
-                    Ok(())
-                }
-                None => Err(Error::<T>::NotStaker),
-            })?;
+            Self::do_stake_more(&staker, staker.clone(), more)
+        }
+
+        /// Unbond some amount. If `amount` exceeds the stake, the whole stake is unbonded.
+        ///
+        /// The unbonded amount stays on hold for `BondingDuration` blocks and then can be withdrawn
+        /// with `withdraw_unbonded`. The remaining stake must be either zero (in which case the
+        /// account stops being a staker and its nominations are cleared) or at least the minimum
+        /// stake.
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::WeightInfo::unbond(T::MaxNominations::get()))]
+        pub fn unbond(origin: OriginFor<T>, amount: u128) -> DispatchResult {
+            let staker = ensure_signed(origin)?;
+            Self::do_unbond(staker, amount)
+        }
+
+        /// Release all the unbonded funds whose bonding duration has already passed.
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::WeightInfo::withdraw_unbonded(T::MaxUnlockingChunks::get()))]
+        pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResult {
+            let staker = ensure_signed(origin)?;
+            let now = frame_system::Pallet::<T>::block_number();
+
+            let mut withdrawn = 0u128;
+            Self::mutate_ledger(&staker, |ledger| {
+                ledger.unlocking.retain(|chunk| {
+                    if chunk.unlock_at > now {
+                        return true;
+                    }
+                    withdrawn = withdrawn.saturating_add(chunk.value);
+                    false
+                });
+            });
+
+            if withdrawn > 0 {
+                T::Currency::release(
+                    &HoldReason::Staking.into(),
+                    &staker,
+                    withdrawn,
+                    Precision::Exact,
+                )?;
+                Self::deposit_event(Event::Withdrawn {
+                    staker,
+                    amount: withdrawn,
+                });
+            }
+            Ok(())
+        }
+
+        /// Stop validating. The stake is not affected, but the nominations of the validator are
+        /// withdrawn.
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::WeightInfo::stop_validating(T::MaxNominators::get()))]
+        pub fn stop_validating(origin: OriginFor<T>) -> DispatchResult {
+            let validator = ensure_signed(origin)?;
+            ensure!(Self::remove_validator(validator), Error::<T>::NotValidator);
+            Ok(())
+        }
+
+        /// Stop taking part in staking actively, i.e. stop validating and nominating. The stake is
+        /// not affected. Unlike `stop_validating`, this call never fails.
+        #[pallet::call_index(7)]
+        #[pallet::weight(
+            T::WeightInfo::chill(T::MaxNominations::get(), T::MaxNominators::get())
+        )]
+        pub fn chill(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::remove_validator(who.clone());
+            Self::remove_nominations(who);
+            Ok(())
+        }
+
+        /// Make `validator` a validator, regardless of their stake. Can only be called by the root.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::WeightInfo::force_add_validator())]
+        pub fn force_add_validator(
+            origin: OriginFor<T>,
+            validator: T::AccountId,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::add_validator(validator, ValidatorPrefs::default())
+        }
+
+        /// Remove `validator` from the validator set, withdrawing its nominations. Can only be
+        /// called by the root.
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::WeightInfo::force_remove_validator(T::MaxNominators::get()))]
+        pub fn force_remove_validator(
+            origin: OriginFor<T>,
+            validator: T::AccountId,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::remove_validator(validator), Error::<T>::NotValidator);
+            Ok(())
+        }
+
+        /// Nominate validators. The whole stake of the caller backs each of the `targets`. Previous
+        /// nominations are replaced.
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::WeightInfo::nominate(targets.len() as u32))]
+        pub fn nominate(origin: OriginFor<T>, targets: Vec<T::AccountId>) -> DispatchResult {
+            let nominator = ensure_signed(origin)?;
+            let stake = Self::active_stake(&nominator).ok_or(Error::<T>::NotStaker)?;
+
+            let mut targets = targets;
+            targets.sort();
+            targets.dedup();
+            ensure!(!targets.is_empty(), Error::<T>::EmptyTargets);
+            let current_targets = Nominations::<T>::get(&nominator);
+            ensure!(
+                current_targets.is_some() || Nominations::<T>::count() < T::MaxNominators::get(),
+                Error::<T>::TooManyNominators
+            );
+            let current_targets = current_targets.unwrap_or_default();
+            for target in &targets {
+                let prefs = Validators::<T>::get(target).ok_or(Error::<T>::NotValidator)?;
+                // Blocked validators can only keep the nominations they already have.
+                ensure!(
+                    !prefs.blocked || current_targets.contains(target),
+                    Error::<T>::ValidatorBlocked
+                );
+            }
+            let targets: BoundedVec<_, T::MaxNominations> =
+                targets.try_into().map_err(|_| Error::<T>::TooManyTargets)?;
+
+            Self::remove_nominations(nominator.clone());
+            for target in &targets {
+                Self::adjust_backing(target, |backing| backing.saturating_add(stake));
+            }
+            Nominations::<T>::insert(&nominator, &targets);
+
+            Self::deposit_event(Event::Nominated {
+                nominator,
+                targets: targets.into_inner(),
+            });
+            Ok(())
+        }
+
+        /// Slash `validator` and all the stakers nominating it by `fraction` of their stakes. The
+        /// slashed funds are burned. Funds that are being unbonded are not slashed. Can only be
+        /// called by the root.
+        ///
+        /// If the stake left after slashing is below the minimum stake, it is unbonded completely.
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::WeightInfo::slash(T::MaxNominators::get()))]
+        pub fn slash(
+            origin: OriginFor<T>,
+            validator: T::AccountId,
+            fraction: Perbill,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let nominators = Nominations::<T>::iter()
+                .filter(|(_, targets)| targets.contains(&validator))
+                .map(|(nominator, _)| nominator)
+                .collect::<Vec<_>>();
+
+            Self::slash_staker(validator, fraction);
+            for nominator in nominators {
+                Self::slash_staker(nominator, fraction);
+            }
+            Ok(())
+        }
+
+        /// Stake some amount on behalf of `beneficiary`. The amount is transferred from the caller
+        /// to `beneficiary` and put on hold there, so the stake belongs to `beneficiary` (the
+        /// stash). Unless the caller stakes for itself, it becomes the controller of the stash.
+        ///
+        /// Only fresh stashes (without a ledger, and therefore without a controller) can be bonded
+        /// for by others. A stash that is already staking can hand over the control of its stake
+        /// with `set_controller` instead.
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::WeightInfo::bond_for())]
+        pub fn bond_for(
+            origin: OriginFor<T>,
+            beneficiary: T::AccountId,
+            amount: u128,
+        ) -> DispatchResult {
+            let payer = ensure_signed(origin)?;
+            ensure!(
+                payer == beneficiary || !Stakers::<T>::contains_key(&beneficiary),
+                Error::<T>::AlreadyBonded
+            );
+            Self::do_stake(&payer, beneficiary.clone(), amount)?;
+            if payer != beneficiary {
+                Self::do_set_controller(beneficiary, Some(payer));
+            }
+            Ok(())
+        }
+
+        /// Set the controller of the caller's stake, or clear it with `None`. The caller must be a
+        /// staker.
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::WeightInfo::set_controller())]
+        pub fn set_controller(
+            origin: OriginFor<T>,
+            controller: Option<T::AccountId>,
+        ) -> DispatchResult {
+            let stash = ensure_signed(origin)?;
+            ensure!(Stakers::<T>::contains_key(&stash), Error::<T>::NotStaker);
+            Self::do_set_controller(stash, controller);
+            Ok(())
+        }
+
+        /// Increase the stake of `stash` by the given amount, which is transferred from the
+        /// caller. The caller must be either the stash or its controller.
+        #[pallet::call_index(14)]
+        #[pallet::weight(
+            T::WeightInfo::stake_more(T::MaxNominations::get())
+                .saturating_add(T::DbWeight::get().reads(1))
+        )]
+        pub fn bond_extra_for(
+            origin: OriginFor<T>,
+            stash: T::AccountId,
+            more: u128,
+        ) -> DispatchResult {
+            let caller = Self::ensure_stash_or_controller(origin, &stash)?;
+            Self::do_stake_more(&caller, stash, more)
+        }
+
+        /// Unbond some amount of the stake of `stash`, exactly like `unbond`. The caller must be
+        /// either the stash or its controller. The funds are withdrawn to the stash.
+        #[pallet::call_index(15)]
+        #[pallet::weight(
+            T::WeightInfo::unbond(T::MaxNominations::get())
+                .saturating_add(T::DbWeight::get().reads(1))
+        )]
+        pub fn unbond_for(
+            origin: OriginFor<T>,
+            stash: T::AccountId,
+            amount: u128,
+        ) -> DispatchResult {
+            Self::ensure_stash_or_controller(origin, &stash)?;
+            Self::do_unbond(stash, amount)
+        }
+
+        /// Become a validator (or stay one) with the given preferences. If `MinValidatorStake` is
+        /// set, the caller must stake at least that much.
+        ///
+        /// The commission is taken from the rewards of the nominators of the validator. A blocked
+        /// validator keeps its current nominations, but does not accept new ones.
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::WeightInfo::validate())]
+        pub fn validate(origin: OriginFor<T>, prefs: ValidatorPrefs) -> DispatchResult {
+            let validator = ensure_signed(origin)?;
+            Self::ensure_validator_stake(&validator)?;
+            Self::add_validator(validator.clone(), prefs)?;
+
+            Validators::<T>::insert(&validator, prefs);
+            Self::deposit_event(Event::ValidatorPrefsSet { validator, prefs });
+            Ok(())
+        }
+
+        /// Elect the active validators right away, without waiting for the next era. Can only be
+        /// called by the root.
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::WeightInfo::force_election(T::MaxValidators::get()))]
+        pub fn force_election(origin: OriginFor<T>) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::elect();
+            Ok(())
+        }
+
+        /// Set the minimum stake. `None` restores the default `Config::MinStake` value. Can only
+        /// be called by the root.
+        ///
+        /// Existing stakes are not affected, even if they are below the new minimum.
+        #[pallet::call_index(5)]
+        #[pallet::weight(T::WeightInfo::set_min_stake())]
+        pub fn set_min_stake(origin: OriginFor<T>, min_stake: Option<u128>) -> DispatchResult {
+            ensure_root(origin)?;
+            MinStakeOverride::<T>::set(min_stake);
+
+            Self::deposit_event(Event::MinStakeSet {
+                min_stake: Self::min_stake(),
+            });
             Ok(())
         }
     }
 
     impl<T: Config> Pallet<T> {
-        /// Check if an account is a validator.
+        /// Check if an account is an elected (active) validator.
         pub fn is_validator(account: <T as frame_system::Config>::AccountId) -> bool {
+            ActiveValidators::<T>::get().contains(&account)
+        }
+
+        /// Check if an account is a validator candidate, i.e. it may be elected at the start of
+        /// the next era.
+        pub fn is_validator_candidate(account: <T as frame_system::Config>::AccountId) -> bool {
             Validators::<T>::contains_key(&account)
         }
 
+        /// The number of elected validators.
+        pub fn validator_count() -> u32 {
+            ActiveValidators::<T>::decode_len().unwrap_or_default() as u32
+        }
+
+        /// The number of validator candidates.
+        pub fn candidate_count() -> u32 {
+            Validators::<T>::count()
+        }
+
+        /// The validators elected for the current era.
+        pub fn active_validators() -> Vec<T::AccountId> {
+            ActiveValidators::<T>::get().into_inner()
+        }
+
+        /// The preferences of `validator`, or `None` if it is not a validator.
+        pub fn validator_prefs(
+            validator: <T as frame_system::Config>::AccountId,
+        ) -> Option<ValidatorPrefs> {
+            Validators::<T>::get(&validator)
+        }
+
+        /// The minimum stake required to become a staker.
+        pub fn min_stake() -> u128 {
+            MinStakeOverride::<T>::get().unwrap_or_else(T::MinStake::get)
+        }
+
+        /// The total stake of the nominators backing `validator`.
+        pub fn backing_of(validator: <T as frame_system::Config>::AccountId) -> u128 {
+            Backing::<T>::get(&validator)
+        }
+
+        /// The sum of the active stakes of all the stakers.
+        pub fn total_stake() -> u128 {
+            TotalStake::<T>::get()
+        }
+
+        /// The number of accounts with a ledger, i.e. the stakers together with the accounts that
+        /// have unbonded everything, but still have some funds to withdraw.
+        pub fn staker_count() -> u32 {
+            Stakers::<T>::count()
+        }
+
         /// Check the stake of an account. Returns `None` if the account is not a staker.
         pub fn stake_of(account: <T as frame_system::Config>::AccountId) -> Option<u128> {
-            Stakers::<T>::get(&account)
+            Self::active_stake(&account)
+        }
+
+        /// The active stake of `staker`, or `None` if it is not a staker.
+        fn active_stake(staker: &T::AccountId) -> Option<u128> {
+            Stakers::<T>::get(staker)
+                .map(|ledger| ledger.active)
+                .filter(|active| *active > 0)
+        }
+
+        /// Mutate the ledger of `staker` (creating it if needed). The ledger is removed if it does
+        /// not hold any funds afterwards.
+        fn mutate_ledger<R>(
+            staker: &T::AccountId,
+            f: impl FnOnce(&mut StakingLedger<T>) -> R,
+        ) -> R {
+            let (result, removed) = Stakers::<T>::mutate_exists(staker, |maybe_ledger| {
+                let mut ledger = maybe_ledger.take().unwrap_or_default();
+                let result = f(&mut ledger);
+                let removed = ledger.is_empty();
+                *maybe_ledger = (!removed).then_some(ledger);
+                (result, removed)
+            });
+            if removed {
+                Controllers::<T>::remove(staker);
+            }
+            result
+        }
+
+        /// The controller of `stash`, if it has one.
+        pub fn controller_of(
+            stash: <T as frame_system::Config>::AccountId,
+        ) -> Option<T::AccountId> {
+            Controllers::<T>::get(&stash)
+        }
+
+        /// Ensure that `origin` is signed either by `stash` or by its controller. Returns the
+        /// signer.
+        fn ensure_stash_or_controller(
+            origin: OriginFor<T>,
+            stash: &T::AccountId,
+        ) -> Result<T::AccountId, DispatchError> {
+            let caller = ensure_signed(origin)?;
+            ensure!(
+                &caller == stash || Controllers::<T>::get(stash).as_ref() == Some(&caller),
+                Error::<T>::NotController
+            );
+            Ok(caller)
+        }
+
+        /// Start staking `stake` for `stash`, with the funds coming from `payer`.
+        pub(crate) fn do_stake(
+            payer: &T::AccountId,
+            stash: T::AccountId,
+            stake: u128,
+        ) -> DispatchResult {
+            ensure!(stake >= Self::min_stake(), Error::<T>::NotEnoughStake);
+            ensure!(Self::active_stake(&stash).is_none(), Error::<T>::AlreadyStaking);
+            ensure!(
+                Stakers::<T>::contains_key(&stash) || Stakers::<T>::count() < T::MaxStakers::get(),
+                Error::<T>::TooManyStakers
+            );
+
+            Self::hold_stake(payer, &stash, stake)?;
+            Self::set_stake(&stash, stake);
+
+            Self::deposit_event(Event::Staked {
+                staker: stash,
+                amount: stake,
+            });
+            Ok(())
+        }
+
+        /// Increase the stake of `stash` by `more`, with the funds coming from `payer`.
+        pub(crate) fn do_stake_more(
+            payer: &T::AccountId,
+            stash: T::AccountId,
+            more: u128,
+        ) -> DispatchResult {
+            let stake = Self::active_stake(&stash).ok_or(Error::<T>::NotStaker)?;
+
+            Self::hold_stake(payer, &stash, more)?;
+            Self::set_stake(&stash, stake.saturating_add(more));
+
+            Self::deposit_event(Event::StakedMore {
+                staker: stash,
+                amount: more,
+            });
+            Ok(())
+        }
+
+        /// Unbond `amount` of the stake of `staker`. See `unbond` for the details.
+        fn do_unbond(staker: T::AccountId, amount: u128) -> DispatchResult {
+            let stake = Self::active_stake(&staker).ok_or(Error::<T>::NotStaker)?;
+
+            let amount = amount.min(stake);
+            let remaining = stake.saturating_sub(amount);
+            ensure!(
+                remaining == 0 || remaining >= Self::min_stake(),
+                Error::<T>::InsufficientRemainingStake
+            );
+            if let Some(min_validator_stake) = T::MinValidatorStake::get() {
+                ensure!(
+                    remaining >= min_validator_stake || !Validators::<T>::contains_key(&staker),
+                    Error::<T>::NotEnoughSelfStake
+                );
+            }
+
+            let unlock_at = frame_system::Pallet::<T>::block_number()
+                .saturating_add(T::BondingDuration::get());
+            Self::mutate_ledger(&staker, |ledger| {
+                ledger
+                    .unlocking
+                    .try_push(UnlockChunk { value: amount, unlock_at })
+                    .map_err(|_| Error::<T>::NoMoreChunks)
+            })?;
+
+            Self::set_stake(&staker, remaining);
+
+            Self::deposit_event(Event::Unbonded { staker, amount });
+            Ok(())
+        }
+
+        /// Set (or clear) the controller of `stash`.
+        fn do_set_controller(stash: T::AccountId, controller: Option<T::AccountId>) {
+            Controllers::<T>::set(&stash, controller.clone());
+            Self::deposit_event(Event::ControllerSet { stash, controller });
+        }
+
+        /// Ensure that `validator` has staked at least `MinValidatorStake` (if it is set).
+        fn ensure_validator_stake(validator: &T::AccountId) -> DispatchResult {
+            if let Some(min_validator_stake) = T::MinValidatorStake::get() {
+                let stake = Self::active_stake(validator).unwrap_or_default();
+                ensure!(stake >= min_validator_stake, Error::<T>::NotEnoughSelfStake);
+            }
+            Ok(())
+        }
+
+        /// Add `validator` with `prefs` to the validator set, respecting `MaxValidators`. Adding
+        /// an existing validator is a no-op (its preferences are kept).
+        fn add_validator(validator: T::AccountId, prefs: ValidatorPrefs) -> DispatchResult {
+            if Validators::<T>::contains_key(&validator) {
+                return Ok(());
+            }
+            ensure!(
+                Validators::<T>::count() < T::MaxValidators::get(),
+                Error::<T>::TooManyValidators
+            );
+
+            Validators::<T>::insert(&validator, prefs);
+            Self::deposit_event(Event::BecameValidator { validator });
+            Ok(())
+        }
+
+        /// Remove `validator` from the validator set (and from the active validators, without
+        /// waiting for the next era). Returns `false` if it was not a validator.
+        ///
+        /// The validator is dropped from the nominations and its backing is cleared, so that it
+        /// starts from scratch if it comes back. Nominators left without any targets stop
+        /// nominating.
+        fn remove_validator(validator: T::AccountId) -> bool {
+            if Validators::<T>::take(&validator).is_none() {
+                return false;
+            }
+            ActiveValidators::<T>::mutate(|active| active.retain(|v| v != &validator));
+
+            let nominators = Nominations::<T>::iter()
+                .filter(|(_, targets)| targets.contains(&validator))
+                .map(|(nominator, _)| nominator)
+                .collect::<Vec<_>>();
+            for nominator in nominators {
+                let stopped = Nominations::<T>::mutate_exists(&nominator, |maybe_targets| {
+                    if let Some(targets) = maybe_targets {
+                        targets.retain(|target| target != &validator);
+                        if targets.is_empty() {
+                            *maybe_targets = None;
+                        }
+                    }
+                    maybe_targets.is_none()
+                });
+                if stopped {
+                    Self::deposit_event(Event::StoppedNominating { nominator });
+                }
+            }
+            Backing::<T>::remove(&validator);
+
+            Self::deposit_event(Event::StoppedValidating { validator });
+            true
+        }
+
+        /// Start a new era and pay out the rewards for the past one. Returns the consumed weight,
+        /// which is bounded by the `MaxStakers` payouts.
+        pub(crate) fn new_era() -> Weight {
+            let era = CurrentEra::<T>::mutate(|era| {
+                *era = era.saturating_add(1);
+                *era
+            });
+
+            let stakers = Stakers::<T>::iter()
+                .map(|(staker, ledger)| (staker, ledger.active))
+                .filter(|(_, stake)| *stake > 0)
+                .collect::<Vec<_>>();
+            let total_stake = TotalStake::<T>::get();
+            let reward = T::RewardPerEra::get();
+
+            if total_stake > 0 {
+                for (staker, stake) in &stakers {
+                    let amount = Perquintill::from_rational(*stake, total_stake) * reward;
+                    Self::pay_out(staker, amount, era);
+                }
+            }
+
+            let candidates = Self::elect();
+            Self::deposit_event(Event::NewEra { era });
+
+            T::WeightInfo::new_era(stakers.len() as u32)
+                .saturating_add(T::WeightInfo::force_election(candidates))
+        }
+
+        /// Elect the `ValidatorCount` validator candidates with the highest self stake plus
+        /// backing as the active validators. Ties are resolved by the account IDs. Returns the
+        /// number of candidates.
+        pub(crate) fn elect() -> u32 {
+            let mut candidates = Validators::<T>::iter_keys()
+                .map(|validator| {
+                    let score = Self::active_stake(&validator)
+                        .unwrap_or_default()
+                        .saturating_add(Backing::<T>::get(&validator));
+                    (validator, score)
+                })
+                .collect::<Vec<_>>();
+            let candidate_count = candidates.len() as u32;
+
+            candidates.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then(a.cmp(b)));
+            let seats = T::ValidatorCount::get().min(T::MaxValidators::get()) as usize;
+            let elected = candidates
+                .into_iter()
+                .take(seats)
+                .map(|(validator, _)| validator)
+                .collect::<Vec<_>>();
+
+            ActiveValidators::<T>::put(BoundedVec::truncate_from(elected.clone()));
+            Self::deposit_event(Event::ValidatorsElected {
+                validators: elected,
+            });
+            candidate_count
+        }
+
+        /// Pay out the `amount` earned by `staker` in `era`. The reward of a nominator is split
+        /// equally between the nominated validators, each of which takes its commission.
+        fn pay_out(staker: &T::AccountId, amount: u128, era: EraIndex) {
+            let mut own_reward = amount;
+            let targets = Nominations::<T>::get(staker).unwrap_or_default();
+            if !targets.is_empty() {
+                let per_target = amount / targets.len() as u128;
+                for target in targets {
+                    let Some(prefs) = Validators::<T>::get(&target) else {
+                        continue;
+                    };
+                    let commission = prefs.commission * per_target;
+                    own_reward = own_reward.saturating_sub(commission);
+                    Self::mint_reward(&target, commission);
+                }
+            }
+
+            if Self::mint_reward(staker, own_reward) {
+                Self::mutate_ledger(staker, |ledger| ledger.last_reward_era = Some(era));
+            }
+        }
+
+        /// Mint `amount` to `who` as a reward. Returns `false` if nothing was minted.
+        fn mint_reward(who: &T::AccountId, amount: u128) -> bool {
+            if amount == 0 || T::Currency::mint_into(who, amount).is_err() {
+                return false;
+            }
+            Self::deposit_event(Event::Rewarded {
+                staker: who.clone(),
+                amount,
+            });
+            true
+        }
+
+        /// Burn `fraction` of the stake of `staker`. If the stake left is below the minimum stake,
+        /// it is unbonded completely.
+        fn slash_staker(staker: T::AccountId, fraction: Perbill) {
+            let Some(stake) = Self::active_stake(&staker) else {
+                return;
+            };
+            let amount = fraction * stake;
+            if amount == 0 {
+                return;
+            }
+
+            let slashed = T::Currency::burn_held(
+                &HoldReason::Staking.into(),
+                &staker,
+                amount,
+                Precision::BestEffort,
+                Fortitude::Force,
+            )
+            .unwrap_or_default();
+
+            let mut remaining = stake.saturating_sub(slashed);
+            if remaining < Self::min_stake() {
+                // Nothing is left to unlock after a full slash, so the ledger goes away once the
+                // stake is zeroed (unless it is still unlocking some older funds).
+                if remaining > 0 {
+                    let unlock_at = frame_system::Pallet::<T>::block_number()
+                        .saturating_add(T::BondingDuration::get());
+                    Self::mutate_ledger(&staker, |ledger| {
+                        if let Err(chunk) = ledger.unlocking.try_push(UnlockChunk {
+                            value: remaining,
+                            unlock_at,
+                        }) {
+                            // No more space for chunks - merge with the latest one.
+                            if let Some(last) = ledger.unlocking.last_mut() {
+                                last.value = last.value.saturating_add(chunk.value);
+                                last.unlock_at = last.unlock_at.max(chunk.unlock_at);
+                            }
+                        }
+                    });
+                }
+                remaining = 0;
+            }
+            Self::set_stake(&staker, remaining);
+
+            Self::deposit_event(Event::Slashed {
+                staker,
+                amount: slashed,
+            });
+        }
+
+        /// Set the stake of `staker` (a zero stake removes the staker together with their
+        /// nominations) and update the backing of the nominated validators accordingly.
+        ///
+        /// The caller is responsible for holding or releasing the funds.
+        pub(crate) fn set_stake(staker: &T::AccountId, stake: u128) {
+            let old_stake = Self::active_stake(staker).unwrap_or_default();
+            TotalStake::<T>::mutate(|total| {
+                *total = total.saturating_sub(old_stake).saturating_add(stake)
+            });
+
+            if stake == 0 {
+                Self::remove_nominations(staker.clone());
+            } else {
+                for target in Nominations::<T>::get(staker).unwrap_or_default() {
+                    Self::adjust_backing(&target, |backing| {
+                        backing.saturating_sub(old_stake).saturating_add(stake)
+                    });
+                }
+            }
+            Self::mutate_ledger(staker, |ledger| ledger.active = stake);
+        }
+
+        /// Clear the nominations of `nominator`, withdrawing its stake from the backing of the
+        /// nominated validators.
+        fn remove_nominations(nominator: T::AccountId) {
+            let Some(targets) = Nominations::<T>::take(&nominator) else {
+                return;
+            };
+            let stake = Self::active_stake(&nominator).unwrap_or_default();
+            for target in &targets {
+                Self::adjust_backing(target, |backing| backing.saturating_sub(stake));
+            }
+            Self::deposit_event(Event::StoppedNominating { nominator });
+        }
+
+        /// Apply `f` to the backing of `validator`, removing the entry if it drops to zero.
+        fn adjust_backing(validator: &T::AccountId, f: impl FnOnce(u128) -> u128) {
+            Backing::<T>::mutate_exists(validator, |backing| {
+                let new_backing = f(backing.unwrap_or_default());
+                *backing = (new_backing > 0).then_some(new_backing);
+            });
+        }
+
+        /// Put `amount` of the free balance of `payer` on hold for `stash`. If they differ, the
+        /// funds are transferred to `stash` first. The payer is kept alive, i.e. the existential
+        /// deposit cannot be staked.
+        ///
+        /// A different `stash` must already exist, since no hold can be placed on an account that
+        /// does not own any funds.
+        fn hold_stake(payer: &T::AccountId, stash: &T::AccountId, amount: u128) -> DispatchResult {
+            let free =
+                T::Currency::reducible_balance(payer, Preservation::Preserve, Fortitude::Polite);
+            ensure!(free >= amount, Error::<T>::InsufficientBalance);
+
+            let reason = HoldReason::Staking.into();
+            if payer == stash {
+                return T::Currency::hold(&reason, stash, amount);
+            }
+            ensure!(T::Currency::hold_available(&reason, stash), Error::<T>::UnfundedStash);
+            T::Currency::transfer_and_hold(
+                &reason,
+                payer,
+                stash,
+                amount,
+                Precision::Exact,
+                Preservation::Preserve,
+                Fortitude::Polite,
+            )
+            .map(|_| ())
+        }
+    }
+
+    #[cfg(any(feature = "try-runtime", test))]
+    impl<T: Config> Pallet<T> {
+        /// Check the invariants of the pallet:
+        /// - no ledger is empty and the funds held by every staker match its ledger,
+        /// - `TotalStake` is the sum of the active stakes, the staker counter is correct and there
+        ///   are at most `Config::MaxStakers` stakers,
+        /// - only stashes with a ledger have a controller,
+        /// - the validator set is within `Config::MaxValidators` and its counter is correct,
+        /// - the active validators are candidates and there are at most `Config::ValidatorCount`
+        ///   of them,
+        /// - there are at most `Config::MaxNominators` nominators, every nominator is a staker
+        ///   nominating only validator candidates, and the backing of every validator is the sum
+        ///   of the stakes of its nominators.
+        ///
+        /// There is no lower bound on the active stakes: the minimum stake can be changed at any
+        /// time with `set_min_stake` and the existing stakes are not affected.
+        pub fn do_try_state() -> Result<(), TryRuntimeError> {
+            let mut total_stake = 0u128;
+            let mut stakers = 0u32;
+            for (staker, ledger) in Stakers::<T>::iter() {
+                total_stake = total_stake.saturating_add(ledger.active);
+                stakers = stakers.saturating_add(1);
+                ensure!(!ledger.is_empty(), "Empty ledger is stored");
+                let locked = ledger
+                    .unlocking
+                    .iter()
+                    .fold(ledger.active, |total, chunk| total.saturating_add(chunk.value));
+                let held = T::Currency::balance_on_hold(&HoldReason::Staking.into(), &staker);
+                ensure!(held == locked, "Held balance does not match the ledger");
+            }
+            ensure!(total_stake == TotalStake::<T>::get(), "Total stake is corrupted");
+            ensure!(stakers == Stakers::<T>::count(), "Staker counter is corrupted");
+            ensure!(stakers <= T::MaxStakers::get(), "Too many stakers");
+
+            for stash in Controllers::<T>::iter_keys() {
+                ensure!(Stakers::<T>::contains_key(&stash), "Controller of a non-staker is stored");
+            }
+
+            let validators = Validators::<T>::iter_keys().count() as u32;
+            ensure!(validators == Validators::<T>::count(), "Validator counter is corrupted");
+            ensure!(validators <= T::MaxValidators::get(), "Too many validators");
+            let active = ActiveValidators::<T>::get();
+            ensure!(
+                active.len() as u32 <= T::ValidatorCount::get(),
+                "Too many active validators"
+            );
+            ensure!(
+                active.iter().all(Validators::<T>::contains_key),
+                "Active validator is not a candidate"
+            );
+
+            let mut expected_backing = BTreeMap::<T::AccountId, u128>::new();
+            for (nominator, targets) in Nominations::<T>::iter() {
+                let stake = Self::active_stake(&nominator).ok_or("Nominator is not a staker")?;
+                for target in targets {
+                    ensure!(
+                        Validators::<T>::contains_key(&target),
+                        "Nominated validator is not a candidate"
+                    );
+                    let backing = expected_backing.entry(target).or_default();
+                    *backing = backing.saturating_add(stake);
+                }
+            }
+            let nominators = Nominations::<T>::iter_keys().count() as u32;
+            ensure!(nominators == Nominations::<T>::count(), "Nomination counter is corrupted");
+            ensure!(nominators <= T::MaxNominators::get(), "Too many nominators");
+            ensure!(
+                Backing::<T>::iter().collect::<BTreeMap<_, _>>() == expected_backing,
+                "Backing does not match the nominations"
+            );
+
+            Ok(())
         }
     }
 }
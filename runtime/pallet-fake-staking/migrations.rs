@@ -0,0 +1,119 @@
+//! Storage migrations for the `FakeStaking` pallet.
+
+/// Migration from the initial storage layout, where `Stakers` only kept the staked amounts, to
+/// [`StakingLedger`](crate::StakingLedger)s.
+pub mod v1 {
+    use frame_support::{pallet_prelude::*, storage_alias, traits::OnRuntimeUpgrade, Twox64Concat};
+    use sp_std::marker::PhantomData;
+
+    use crate::{Config, Pallet, StakingLedger, Stakers};
+
+    /// The storage items as they were laid out in version 0.
+    mod v0 {
+        use super::*;
+
+        /// The validators, stored without any preferences.
+        #[storage_alias]
+        pub type Validators<T: Config> = CountedStorageMap<
+            Pallet<T>,
+            Twox64Concat,
+            <T as frame_system::Config>::AccountId,
+            (),
+        >;
+    }
+
+    /// Turn the stakes in `Stakers` into ledgers without any unbonding chunks. Since version 0
+    /// did not count the entries of `Stakers` and `Validators`, their counters are initialized as
+    /// well.
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 0 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut translated = 0u64;
+            Stakers::<T>::translate::<u128, _>(|_, active| {
+                translated = translated.saturating_add(1);
+                Some(StakingLedger {
+                    active,
+                    ..Default::default()
+                })
+            });
+            Stakers::<T>::initialize_counter();
+            let validators = u64::from(v0::Validators::<T>::initialize_counter());
+
+            StorageVersion::new(1).put::<Pallet<T>>();
+
+            // Every ledger is read twice (once to count it), every validator is read once. On top
+            // of that, the two counters and the storage version are written.
+            T::DbWeight::get().reads_writes(
+                translated.saturating_mul(2).saturating_add(validators).saturating_add(1),
+                translated.saturating_add(3),
+            )
+        }
+    }
+}
+
+/// Migration that starts tracking the total stake.
+pub mod v2 {
+    use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+    use sp_std::marker::PhantomData;
+
+    use crate::{Config, Pallet, Stakers, TotalStake};
+
+    /// Initialize `TotalStake` from the existing ledgers.
+    pub struct MigrateToV2<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 1 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut stakers = 0u64;
+            let total_stake = Stakers::<T>::iter_values().fold(0u128, |total, ledger| {
+                stakers = stakers.saturating_add(1);
+                total.saturating_add(ledger.active)
+            });
+            TotalStake::<T>::put(total_stake);
+
+            StorageVersion::new(2).put::<Pallet<T>>();
+
+            T::DbWeight::get().reads_writes(stakers.saturating_add(1), 2)
+        }
+    }
+}
+
+/// Migration that attaches the preferences to the validators.
+pub mod v3 {
+    use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade};
+    use sp_std::marker::PhantomData;
+
+    use crate::{Config, Pallet, ValidatorPrefs, Validators};
+
+    /// Give every validator the default preferences: no commission, open for nominations.
+    pub struct MigrateToV3<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 2 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut translated = 0u64;
+            Validators::<T>::translate::<(), _>(|_, ()| {
+                translated = translated.saturating_add(1);
+                Some(ValidatorPrefs::default())
+            });
+
+            StorageVersion::new(3).put::<Pallet<T>>();
+
+            T::DbWeight::get().reads_writes(
+                translated.saturating_add(1),
+                translated.saturating_add(1),
+            )
+        }
+    }
+}
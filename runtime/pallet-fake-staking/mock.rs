@@ -0,0 +1,76 @@
+//! A minimal runtime for the unit tests and the benchmark test suite of the pallet.
+
+use frame_support::{
+    construct_runtime, derive_impl, parameter_types,
+    sp_runtime::BuildStorage,
+    traits::{ConstU128, ConstU32, ConstU64},
+};
+
+use crate as pallet_fake_staking;
+
+construct_runtime!(
+    pub enum Test {
+        System: frame_system,
+        Balances: pallet_balances,
+        FakeStaking: pallet_fake_staking,
+    }
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig as frame_system::DefaultConfig)]
+impl frame_system::Config for Test {
+    type Block = frame_system::mocking::MockBlock<Test>;
+    type AccountData = pallet_balances::AccountData<u128>;
+}
+
+impl pallet_balances::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = u128;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type FreezeIdentifier = ();
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type MaxFreezes = ();
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type RuntimeFreezeReason = RuntimeFreezeReason;
+}
+
+parameter_types! {
+    pub const MinValidatorStake: Option<u128> = None;
+}
+
+impl pallet_fake_staking::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type MinStake = ConstU128<100>;
+    type BondingDuration = ConstU64<10>;
+    type MaxUnlockingChunks = ConstU32<32>;
+    type MaxValidators = ConstU32<100>;
+    type MinValidatorStake = MinValidatorStake;
+    type MaxNominations = ConstU32<16>;
+    type MaxNominators = ConstU32<64>;
+    type EraDuration = ConstU64<10>;
+    type RewardPerEra = ConstU128<1_000>;
+    type MaxStakers = ConstU32<128>;
+    type ValidatorCount = ConstU32<10>;
+    type WeightInfo = ();
+}
+
+/// Build the externalities of a fresh `Test` runtime, at block 1 (so that events are recorded).
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    test_ext_from(RuntimeGenesisConfig::default())
+}
+
+/// Build the externalities of a `Test` runtime from `genesis`, at block 1.
+pub fn test_ext_from(genesis: RuntimeGenesisConfig) -> sp_io::TestExternalities {
+    let storage = genesis
+        .build_storage()
+        .expect("Failed to build the genesis storage");
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -0,0 +1,513 @@
+//! Unit tests of the pallet, run against the `Test` runtime from `mock.rs`.
+
+use frame_support::{
+    assert_noop, assert_ok,
+    sp_runtime::{DispatchError, Perbill},
+    traits::{
+        fungible::{InspectHold, Mutate},
+        Get, Hooks,
+    },
+};
+
+use crate::{mock::*, Error, HoldReason, Nominations, Stakers, StakingInterface, ValidatorPrefs};
+
+const BOB: u64 = 2;
+const CHARLIE: u64 = 3;
+const DAVE: u64 = 4;
+
+fn signed(who: u64) -> RuntimeOrigin {
+    RuntimeOrigin::signed(who)
+}
+
+fn fund(who: u64, amount: u128) {
+    Balances::set_balance(&who, amount);
+}
+
+fn held_stake_of(who: u64) -> u128 {
+    Balances::balance_on_hold(&HoldReason::Staking.into(), &who)
+}
+
+/// Initialize the blocks up to `n`, starting new eras on the way, and check the invariants of the
+/// pallet after each of them.
+fn run_to_block(n: u64) {
+    while System::block_number() < n {
+        let next = System::block_number() + 1;
+        System::set_block_number(next);
+        FakeStaking::on_initialize(next);
+        FakeStaking::do_try_state().unwrap();
+    }
+}
+
+#[test]
+fn cannot_stake_more_than_free_balance() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 150);
+
+        assert_noop!(FakeStaking::stake(signed(BOB), 200), Error::<Test>::InsufficientBalance);
+        assert_eq!(held_stake_of(BOB), 0);
+    });
+}
+
+#[test]
+fn unbonded_funds_can_be_withdrawn_after_bonding_duration() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::unbond(signed(BOB), 300));
+        assert_eq!(FakeStaking::stake_of(BOB), None);
+        assert_eq!(held_stake_of(BOB), 300);
+
+        assert_ok!(FakeStaking::withdraw_unbonded(signed(BOB)));
+        assert_eq!(held_stake_of(BOB), 300);
+
+        // `BondingDuration` is 10 blocks.
+        run_to_block(11);
+
+        assert_ok!(FakeStaking::withdraw_unbonded(signed(BOB)));
+        assert_eq!(held_stake_of(BOB), 0);
+    });
+}
+
+#[test]
+fn cannot_unbond_below_threshold() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        assert_ok!(FakeStaking::stake(signed(BOB), 150));
+
+        assert_noop!(
+            FakeStaking::unbond(signed(BOB), 100),
+            Error::<Test>::InsufficientRemainingStake
+        );
+        assert_eq!(FakeStaking::stake_of(BOB), Some(150));
+    });
+}
+
+#[test]
+fn genesis_stakers_have_their_stake_held() {
+    let mut genesis = RuntimeGenesisConfig::default();
+    genesis.balances.balances.push((BOB, 1_000));
+    genesis.fake_staking.stakers.push((BOB, 300));
+    genesis.fake_staking.validators.push(BOB);
+
+    test_ext_from(genesis).execute_with(|| {
+        assert_eq!(FakeStaking::stake_of(BOB), Some(300));
+        assert!(FakeStaking::is_validator(BOB));
+        assert_eq!(held_stake_of(BOB), 300);
+        assert_eq!(Balances::free_balance(BOB), 700);
+    });
+}
+
+#[test]
+fn root_can_raise_min_stake() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(FakeStaking::set_min_stake(RuntimeOrigin::root(), Some(500)));
+        assert_eq!(FakeStaking::min_stake(), 500);
+
+        assert_noop!(FakeStaking::stake(signed(BOB), 300), Error::<Test>::NotEnoughStake);
+    });
+}
+
+#[test]
+fn validator_can_stop_validating() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(FakeStaking::become_validator(signed(BOB)));
+        assert!(FakeStaking::is_validator_candidate(BOB));
+        assert_eq!(FakeStaking::candidate_count(), 1);
+
+        assert_ok!(FakeStaking::stop_validating(signed(BOB)));
+        assert!(!FakeStaking::is_validator_candidate(BOB));
+        assert_eq!(FakeStaking::candidate_count(), 0);
+
+        assert_noop!(FakeStaking::stop_validating(signed(BOB)), Error::<Test>::NotValidator);
+        assert_ok!(FakeStaking::chill(signed(BOB)));
+    });
+}
+
+#[test]
+fn only_root_can_force_validators() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            FakeStaking::force_add_validator(signed(BOB), BOB),
+            DispatchError::BadOrigin
+        );
+
+        assert_ok!(FakeStaking::force_add_validator(RuntimeOrigin::root(), BOB));
+        assert!(FakeStaking::is_validator_candidate(BOB));
+
+        assert_ok!(FakeStaking::force_remove_validator(RuntimeOrigin::root(), BOB));
+        assert!(!FakeStaking::is_validator_candidate(BOB));
+    });
+}
+
+#[test]
+fn nominations_back_validators() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(FakeStaking::force_add_validator(RuntimeOrigin::root(), CHARLIE));
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::nominate(signed(BOB), vec![CHARLIE]));
+        assert_eq!(FakeStaking::backing_of(CHARLIE), 300);
+
+        assert_ok!(FakeStaking::stake_more(signed(BOB), 100));
+        assert_eq!(FakeStaking::backing_of(CHARLIE), 400);
+
+        assert_ok!(FakeStaking::chill(signed(BOB)));
+        assert_eq!(FakeStaking::backing_of(CHARLIE), 0);
+    });
+}
+
+#[test]
+fn removed_validator_loses_its_nominations() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        let nominations_of_bob = || Nominations::<Test>::get(BOB).map(|t| t.into_inner());
+
+        assert_ok!(FakeStaking::force_add_validator(RuntimeOrigin::root(), CHARLIE));
+        assert_ok!(FakeStaking::force_add_validator(RuntimeOrigin::root(), DAVE));
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::nominate(signed(BOB), vec![CHARLIE, DAVE]));
+
+        assert_ok!(FakeStaking::force_remove_validator(RuntimeOrigin::root(), CHARLIE));
+        assert_eq!(FakeStaking::backing_of(CHARLIE), 0);
+        assert_eq!(FakeStaking::backing_of(DAVE), 300);
+        assert_eq!(nominations_of_bob(), Some(vec![DAVE]));
+
+        // Without any targets left, Bob stops nominating.
+        assert_ok!(FakeStaking::force_remove_validator(RuntimeOrigin::root(), DAVE));
+        assert_eq!(FakeStaking::backing_of(DAVE), 0);
+        assert_eq!(nominations_of_bob(), None);
+    });
+}
+
+#[test]
+fn stakers_are_rewarded_every_era() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        fund(CHARLIE, 1_000);
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 100));
+
+        // The first era starts at block 10 and `RewardPerEra` is 1000.
+        run_to_block(10);
+
+        assert_eq!(crate::CurrentEra::<Test>::get(), 1);
+        assert_eq!(Balances::free_balance(BOB), 700 + 750);
+        assert_eq!(Balances::free_balance(CHARLIE), 900 + 250);
+    });
+}
+
+#[test]
+fn number_of_stakers_is_bounded() {
+    new_test_ext().execute_with(|| {
+        let max_stakers = <<Test as crate::Config>::MaxStakers as Get<u32>>::get();
+        for staker in 100..100 + u64::from(max_stakers) {
+            fund(staker, 1_000);
+            assert_ok!(FakeStaking::stake(signed(staker), 100));
+        }
+
+        fund(BOB, 1_000);
+        assert_noop!(FakeStaking::stake(signed(BOB), 100), Error::<Test>::TooManyStakers);
+
+        // The stakers that are already there can still stake more.
+        assert_ok!(FakeStaking::stake_more(signed(100), 100));
+    });
+}
+
+#[test]
+fn slashing_hits_validator_and_nominators() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        fund(CHARLIE, 2_000);
+
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 1_000));
+        assert_ok!(FakeStaking::become_validator(signed(CHARLIE)));
+        assert_ok!(FakeStaking::stake(signed(BOB), 500));
+        assert_ok!(FakeStaking::nominate(signed(BOB), vec![CHARLIE]));
+
+        assert_ok!(FakeStaking::slash(
+            RuntimeOrigin::root(),
+            CHARLIE,
+            Perbill::from_percent(10)
+        ));
+
+        assert_eq!(FakeStaking::stake_of(CHARLIE), Some(900));
+        assert_eq!(FakeStaking::stake_of(BOB), Some(450));
+        assert_eq!(FakeStaking::backing_of(CHARLIE), 450);
+        assert_eq!(held_stake_of(CHARLIE), 900);
+        assert_eq!(held_stake_of(BOB), 450);
+    });
+}
+
+#[test]
+fn full_slash_removes_the_staker() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(FakeStaking::stake(signed(BOB), 500));
+        assert_ok!(FakeStaking::slash(RuntimeOrigin::root(), BOB, Perbill::from_percent(100)));
+
+        assert_eq!(FakeStaking::stake_of(BOB), None);
+        assert_eq!(held_stake_of(BOB), 0);
+        // No empty ledger is left behind.
+        assert!(!Stakers::<Test>::contains_key(BOB));
+        FakeStaking::do_try_state().unwrap();
+    });
+}
+
+#[test]
+fn v0_stakes_are_migrated_to_ledgers() {
+    use frame_support::{
+        storage::unhashed,
+        traits::{fungible::MutateHold, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+    };
+
+    use crate::{
+        migrations::{v1::MigrateToV1, v2::MigrateToV2, v3::MigrateToV3},
+        StakingLedger, Validators,
+    };
+
+    new_test_ext().execute_with(|| {
+        // Write the storage as it was laid out in version 0: bare stakes and validators without
+        // any preferences, neither of them counted.
+        StorageVersion::new(0).put::<FakeStaking>();
+        fund(BOB, 1_000);
+        assert_ok!(Balances::hold(&HoldReason::Staking.into(), &BOB, 300));
+        unhashed::put(&Stakers::<Test>::hashed_key_for(BOB), &300u128);
+        unhashed::put_raw(&Validators::<Test>::hashed_key_for(CHARLIE), &[]);
+
+        MigrateToV1::<Test>::on_runtime_upgrade();
+
+        assert_eq!(FakeStaking::on_chain_storage_version(), 1);
+        assert_eq!(
+            Stakers::<Test>::get(BOB),
+            Some(StakingLedger {
+                active: 300,
+                ..Default::default()
+            })
+        );
+        assert_eq!(Stakers::<Test>::count(), 1);
+        assert_eq!(Validators::<Test>::count(), 1);
+
+        MigrateToV2::<Test>::on_runtime_upgrade();
+
+        assert_eq!(FakeStaking::on_chain_storage_version(), 2);
+        assert_eq!(FakeStaking::total_stake(), 300);
+
+        MigrateToV3::<Test>::on_runtime_upgrade();
+
+        assert_eq!(FakeStaking::on_chain_storage_version(), 3);
+        assert_eq!(FakeStaking::validator_prefs(CHARLIE), Some(Default::default()));
+        assert_eq!(Validators::<Test>::count(), 1);
+
+        FakeStaking::do_try_state().unwrap();
+    });
+}
+
+#[test]
+fn corrupted_state_is_detected() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::do_try_state());
+
+        // Pretend that the stake has grown without putting more funds on hold.
+        Stakers::<Test>::mutate(BOB, |ledger| ledger.as_mut().unwrap().active = 400);
+        assert!(FakeStaking::do_try_state().is_err());
+    });
+}
+
+#[test]
+fn stakes_below_a_raised_min_stake_are_kept() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(FakeStaking::set_min_stake(RuntimeOrigin::root(), Some(50)));
+        assert_ok!(FakeStaking::stake(signed(BOB), 60));
+        // Restore the default minimum of 100, which is above Bob's stake.
+        assert_ok!(FakeStaking::set_min_stake(RuntimeOrigin::root(), None));
+
+        assert_eq!(FakeStaking::stake_of(BOB), Some(60));
+        // The state checks after every block accept the stake as well.
+        run_to_block(3);
+    });
+}
+
+#[test]
+fn controller_manages_stake_bonded_for_others() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        // The existential deposit is enough for Charlie to hold the stake.
+        fund(CHARLIE, 1);
+
+        assert_ok!(FakeStaking::bond_for(signed(BOB), CHARLIE, 300));
+        assert_eq!(FakeStaking::controller_of(CHARLIE), Some(BOB));
+
+        assert_ok!(FakeStaking::bond_extra_for(signed(BOB), CHARLIE, 100));
+        assert_noop!(
+            FakeStaking::unbond_for(signed(DAVE), CHARLIE, 100),
+            Error::<Test>::NotController
+        );
+        assert_ok!(FakeStaking::unbond_for(signed(BOB), CHARLIE, 100));
+
+        assert_eq!(FakeStaking::stake_of(CHARLIE), Some(300));
+        assert_eq!(FakeStaking::stake_of(BOB), None);
+        assert_eq!(held_stake_of(CHARLIE), 400);
+        assert_eq!(Balances::free_balance(BOB), 600);
+    });
+}
+
+#[test]
+fn cannot_bond_for_unfunded_stash() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        // Charlie has never received any funds, so there is no account to hold the stake.
+        assert_noop!(
+            FakeStaking::bond_for(signed(BOB), CHARLIE, 300),
+            Error::<Test>::UnfundedStash
+        );
+
+        fund(CHARLIE, 1);
+        assert_ok!(FakeStaking::bond_for(signed(BOB), CHARLIE, 300));
+        assert_eq!(held_stake_of(CHARLIE), 300);
+    });
+}
+
+#[test]
+fn only_fresh_stashes_can_be_bonded_for() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        fund(CHARLIE, 1_000);
+        fund(DAVE, 1);
+
+        // Charlie stakes on their own, so nobody can take over their stake.
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 300));
+        assert_noop!(
+            FakeStaking::bond_for(signed(BOB), CHARLIE, 300),
+            Error::<Test>::AlreadyBonded
+        );
+        assert_eq!(FakeStaking::controller_of(CHARLIE), None);
+
+        // Once Bob controls a stash, nobody else can take over the stash.
+        assert_ok!(FakeStaking::bond_for(signed(BOB), DAVE, 300));
+        assert_noop!(
+            FakeStaking::bond_for(signed(CHARLIE), DAVE, 300),
+            Error::<Test>::AlreadyBonded
+        );
+        assert_eq!(FakeStaking::controller_of(DAVE), Some(BOB));
+    });
+}
+
+#[test]
+fn pallet_implements_staking_interface() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+
+        assert_ok!(<FakeStaking as StakingInterface>::stake(&BOB, 300));
+        assert_ok!(<FakeStaking as StakingInterface>::stake_more(&BOB, 100));
+        assert_eq!(<FakeStaking as StakingInterface>::active_stake(&BOB), Some(400));
+        assert_eq!(held_stake_of(BOB), 400);
+
+        assert_ok!(FakeStaking::become_validator(signed(BOB)));
+        assert!(<FakeStaking as StakingInterface>::is_validator_candidate(&BOB));
+        assert!(!<FakeStaking as StakingInterface>::is_validator(&BOB));
+
+        assert_ok!(FakeStaking::force_election(RuntimeOrigin::root()));
+        assert!(<FakeStaking as StakingInterface>::is_validator(&BOB));
+        assert_eq!(<FakeStaking as StakingInterface>::validators(), vec![BOB]);
+    });
+}
+
+#[test]
+fn total_stake_and_staker_count_are_tracked() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        fund(CHARLIE, 1_000);
+
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 200));
+        assert_ok!(FakeStaking::stake_more(signed(BOB), 100));
+        assert_eq!(FakeStaking::total_stake(), 600);
+        assert_eq!(FakeStaking::staker_count(), 2);
+
+        assert_ok!(FakeStaking::unbond(signed(CHARLIE), 200));
+        assert_eq!(FakeStaking::total_stake(), 400);
+
+        run_to_block(11);
+
+        assert_ok!(FakeStaking::withdraw_unbonded(signed(CHARLIE)));
+        assert_eq!(FakeStaking::staker_count(), 1);
+    });
+}
+
+#[test]
+fn validator_takes_commission_from_nominators() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        fund(CHARLIE, 1_000);
+
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 300));
+        let prefs = ValidatorPrefs {
+            commission: Perbill::from_percent(50),
+            blocked: false,
+        };
+        assert_ok!(FakeStaking::validate(signed(BOB), prefs));
+        assert_eq!(FakeStaking::validator_prefs(BOB), Some(prefs));
+        assert_ok!(FakeStaking::nominate(signed(CHARLIE), vec![BOB]));
+
+        // Both earn 500, but half of the reward of Charlie goes to Bob.
+        run_to_block(10);
+
+        assert_eq!(Balances::free_balance(BOB), 700 + 500 + 250);
+        assert_eq!(Balances::free_balance(CHARLIE), 700 + 250);
+    });
+}
+
+#[test]
+fn blocked_validator_cannot_be_nominated() {
+    new_test_ext().execute_with(|| {
+        fund(CHARLIE, 1_000);
+
+        let prefs = ValidatorPrefs {
+            commission: Perbill::zero(),
+            blocked: true,
+        };
+        assert_ok!(FakeStaking::validate(signed(BOB), prefs));
+        assert_ok!(FakeStaking::stake(signed(CHARLIE), 300));
+
+        assert_noop!(
+            FakeStaking::nominate(signed(CHARLIE), vec![BOB]),
+            Error::<Test>::ValidatorBlocked
+        );
+    });
+}
+
+#[test]
+fn validators_with_most_stake_are_elected_every_era() {
+    new_test_ext().execute_with(|| {
+        fund(BOB, 1_000);
+        // Together with Bob there are 11 candidates for 10 seats (`ValidatorCount`).
+        let others = (10u64..20).collect::<Vec<_>>();
+
+        for validator in &others {
+            assert_ok!(FakeStaking::force_add_validator(RuntimeOrigin::root(), *validator));
+        }
+        assert_ok!(FakeStaking::stake(signed(BOB), 300));
+        assert_ok!(FakeStaking::become_validator(signed(BOB)));
+        assert_eq!(FakeStaking::validator_count(), 0);
+
+        run_to_block(10);
+
+        assert_eq!(FakeStaking::validator_count(), 10);
+        assert!(FakeStaking::is_validator(BOB));
+        // The candidates without any stake are ordered by their account IDs.
+        assert!(FakeStaking::is_validator(others[8]));
+        assert!(!FakeStaking::is_validator(others[9]));
+        assert!(FakeStaking::is_validator_candidate(others[9]));
+    });
+}
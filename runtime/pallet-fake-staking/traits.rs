@@ -0,0 +1,155 @@
+//! Traits through which other components of the runtime can use the staking system.
+
+use frame_support::{
+    pallet_prelude::{DispatchError, DispatchResult},
+    traits::Get,
+    weights::Weight,
+};
+use sp_std::vec::Vec;
+
+use crate::{
+    ActiveValidators, Config, Error, Pallet, Stakers, TotalStake, ValidatorPrefs, Validators,
+    WeightInfo,
+};
+
+/// The reasons for which `StakingInterface::stake` and `StakingInterface::stake_more` can fail in
+/// every staking system, so that the callers can handle them without knowing the system.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StakingError {
+    /// The stake is below the minimum stake.
+    NotEnoughStake,
+    /// The account is already a staker.
+    AlreadyStaking,
+    /// The account is not a staker.
+    NotStaker,
+    /// The free balance of the account is not enough to cover the stake.
+    InsufficientBalance,
+}
+
+/// A generic view of a staking system, modelled on `sp_staking::StakingInterface`.
+///
+/// Other pallets and the chain extension should depend on this trait rather than on
+/// `pallet_fake_staking` directly, so that they can be pointed at a mock in tests.
+pub trait StakingInterface {
+    /// The type of the staking accounts.
+    type AccountId;
+    /// The type of the staked amounts.
+    type Balance;
+    /// The type of the preferences of the validators.
+    type ValidatorPrefs;
+
+    /// The minimum amount that has to be staked to become a staker.
+    fn minimum_stake() -> Self::Balance;
+
+    /// Start staking `value` of the free funds of `who`.
+    fn stake(who: &Self::AccountId, value: Self::Balance) -> DispatchResult;
+
+    /// Increase the stake of `who` by `extra` of its free funds.
+    fn stake_more(who: &Self::AccountId, extra: Self::Balance) -> DispatchResult;
+
+    /// The maximum weight of `stake`.
+    fn stake_weight() -> Weight;
+
+    /// The maximum weight of `stake_more`.
+    fn stake_more_weight() -> Weight;
+
+    /// Recognize an error returned by `stake` or `stake_more`. `None` if it is none of the common
+    /// `StakingError`s.
+    fn staking_error(error: &DispatchError) -> Option<StakingError>;
+
+    /// The active stake of `who`, or `None` if it is not a staker.
+    fn active_stake(who: &Self::AccountId) -> Option<Self::Balance>;
+
+    /// Whether `who` is an elected (active) validator.
+    fn is_validator(who: &Self::AccountId) -> bool;
+
+    /// Whether `who` is a validator candidate, i.e. it may be elected in the future.
+    fn is_validator_candidate(who: &Self::AccountId) -> bool;
+
+    /// All the elected validators.
+    fn validators() -> Vec<Self::AccountId>;
+
+    /// The number of elected validators.
+    fn validator_count() -> u32;
+
+    /// The preferences of `who`, or `None` if it is not a validator candidate.
+    fn validator_prefs(who: &Self::AccountId) -> Option<Self::ValidatorPrefs>;
+
+    /// The sum of the active stakes of all the stakers.
+    fn total_stake() -> Self::Balance;
+
+    /// The number of stakers (including the ones that only have some funds left to withdraw).
+    fn staker_count() -> u32;
+}
+
+impl<T: Config> StakingInterface for Pallet<T> {
+    type AccountId = T::AccountId;
+    type Balance = u128;
+    type ValidatorPrefs = ValidatorPrefs;
+
+    fn minimum_stake() -> u128 {
+        Self::min_stake()
+    }
+
+    fn stake(who: &T::AccountId, value: u128) -> DispatchResult {
+        Self::do_stake(who, who.clone(), value)
+    }
+
+    fn stake_more(who: &T::AccountId, extra: u128) -> DispatchResult {
+        Self::do_stake_more(who, who.clone(), extra)
+    }
+
+    fn stake_weight() -> Weight {
+        T::WeightInfo::stake()
+    }
+
+    fn stake_more_weight() -> Weight {
+        // Increasing the stake updates the backing of every nominated validator.
+        T::WeightInfo::stake_more(T::MaxNominations::get())
+    }
+
+    fn staking_error(error: &DispatchError) -> Option<StakingError> {
+        [
+            (Error::<T>::NotEnoughStake, StakingError::NotEnoughStake),
+            (Error::<T>::AlreadyStaking, StakingError::AlreadyStaking),
+            (Error::<T>::NotStaker, StakingError::NotStaker),
+            (Error::<T>::InsufficientBalance, StakingError::InsufficientBalance),
+        ]
+        .into_iter()
+        .find_map(|(pallet_error, staking_error)| {
+            (*error == DispatchError::from(pallet_error)).then_some(staking_error)
+        })
+    }
+
+    fn active_stake(who: &T::AccountId) -> Option<u128> {
+        Self::stake_of(who.clone())
+    }
+
+    fn is_validator(who: &T::AccountId) -> bool {
+        ActiveValidators::<T>::get().contains(who)
+    }
+
+    fn is_validator_candidate(who: &T::AccountId) -> bool {
+        Validators::<T>::contains_key(who)
+    }
+
+    fn validators() -> Vec<T::AccountId> {
+        Self::active_validators()
+    }
+
+    fn validator_count() -> u32 {
+        ActiveValidators::<T>::decode_len().unwrap_or_default() as u32
+    }
+
+    fn validator_prefs(who: &T::AccountId) -> Option<ValidatorPrefs> {
+        Validators::<T>::get(who)
+    }
+
+    fn total_stake() -> u128 {
+        TotalStake::<T>::get()
+    }
+
+    fn staker_count() -> u32 {
+        Stakers::<T>::count()
+    }
+}
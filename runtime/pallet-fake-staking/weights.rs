@@ -0,0 +1,190 @@
+//! Weights for `pallet_fake_staking`.
+//!
+//! These are NOT benchmark results. Every weight below is a rough estimate that only prices the
+//! storage reads and writes of a call (counted by hand from the code) with the database weights,
+//! and ignores the computation. Replace them with weights generated from the benchmarks in
+//! `benchmarking.rs` once those can be run on the reference hardware.
+
+use core::marker::PhantomData;
+
+use frame_support::{
+    traits::Get,
+    weights::{constants::RocksDbWeight, RuntimeDbWeight, Weight},
+};
+
+/// Weight functions needed for `pallet_fake_staking`.
+pub trait WeightInfo {
+    fn become_validator() -> Weight;
+    fn stake() -> Weight;
+    fn stake_more(n: u32) -> Weight;
+    fn unbond(n: u32) -> Weight;
+    fn withdraw_unbonded(u: u32) -> Weight;
+    fn stop_validating(m: u32) -> Weight;
+    fn chill(n: u32, m: u32) -> Weight;
+    fn force_add_validator() -> Weight;
+    fn force_remove_validator(m: u32) -> Weight;
+    fn nominate(n: u32) -> Weight;
+    fn slash(n: u32) -> Weight;
+    fn set_min_stake() -> Weight;
+    fn new_era(s: u32) -> Weight;
+    fn bond_for() -> Weight;
+    fn set_controller() -> Weight;
+    fn validate() -> Weight;
+    fn force_election(v: u32) -> Weight;
+}
+
+/// Estimated weights priced with the database weights `W`.
+pub struct DbWeightEstimate<W>(PhantomData<W>);
+
+/// Estimated weights priced with the database weights of the runtime.
+pub type SubstrateWeight<T> = DbWeightEstimate<<T as frame_system::Config>::DbWeight>;
+
+impl<W: Get<RuntimeDbWeight>> DbWeightEstimate<W> {
+    /// The weight of `reads` reads and `writes` writes, plus `per_item_reads` reads and
+    /// `per_item_writes` writes for each of the `items`.
+    fn db(
+        reads: u64,
+        writes: u64,
+        items: u32,
+        per_item_reads: u64,
+        per_item_writes: u64,
+    ) -> Weight {
+        let items = u64::from(items);
+        W::get().reads_writes(
+            reads.saturating_add(per_item_reads.saturating_mul(items)),
+            writes.saturating_add(per_item_writes.saturating_mul(items)),
+        )
+    }
+}
+
+impl<W: Get<RuntimeDbWeight>> WeightInfo for DbWeightEstimate<W> {
+    fn become_validator() -> Weight {
+        // The validator, its counter and the ledger of the caller.
+        Self::db(3, 2, 0, 0, 0)
+    }
+    fn stake() -> Weight {
+        // The ledger and its counter, the minimum stake, the nominations, the hold, the account
+        // and the total stake.
+        Self::db(7, 5, 0, 0, 0)
+    }
+    fn stake_more(n: u32) -> Weight {
+        // The ledger, the nominations, the hold, the account and the total stake, plus the backing
+        // of every nominated validator.
+        Self::db(5, 4, n, 1, 1)
+    }
+    fn unbond(n: u32) -> Weight {
+        // The ledger, the controller, the minimum stake, the validator, the nominations and their
+        // counter and the total stake, plus the backing of every nominated validator.
+        Self::db(6, 5, n, 1, 1)
+    }
+    fn withdraw_unbonded(_u: u32) -> Weight {
+        // The ledger and its counter, the controller, the hold and the account. The chunks are
+        // stored in the ledger, so their number does not matter.
+        Self::db(4, 5, 0, 0, 0)
+    }
+    fn stop_validating(m: u32) -> Weight {
+        // The validator and its counter, the active validators, the nomination counter and the
+        // backing, plus the nominations of every nominator.
+        Self::db(4, 4, m, 1, 1)
+    }
+    fn chill(n: u32, m: u32) -> Weight {
+        // `stop_validating` with `m` nominators of the caller, plus the backing of every validator
+        // nominated by the caller.
+        Self::stop_validating(m).saturating_add(Self::db(2, 2, n, 1, 1))
+    }
+    fn force_add_validator() -> Weight {
+        // The validator and its counter.
+        Self::db(2, 2, 0, 0, 0)
+    }
+    fn force_remove_validator(m: u32) -> Weight {
+        Self::stop_validating(m)
+    }
+    fn nominate(n: u32) -> Weight {
+        // The ledger and the nominations with their counter, plus every target and its backing.
+        Self::db(3, 2, n, 2, 1)
+    }
+    fn slash(n: u32) -> Weight {
+        // For the validator and each of the `n` nominators: the nominations, the ledger, the
+        // backing, the hold and the account. On top of that, the minimum stake.
+        Self::db(6, 4, n, 5, 4)
+    }
+    fn set_min_stake() -> Weight {
+        // The minimum stake.
+        Self::db(0, 1, 0, 0, 0)
+    }
+    fn new_era(s: u32) -> Weight {
+        // The era, the total stake and the end of the iteration, plus the ledger, the nominations
+        // and the account of every staker.
+        Self::db(3, 1, s, 3, 2)
+    }
+    fn bond_for() -> Weight {
+        // `stake` with both accounts and the controller.
+        Self::db(8, 7, 0, 0, 0)
+    }
+    fn set_controller() -> Weight {
+        // The ledger and the controller.
+        Self::db(1, 1, 0, 0, 0)
+    }
+    fn validate() -> Weight {
+        Self::become_validator()
+    }
+    fn force_election(v: u32) -> Weight {
+        // The active validators, plus every candidate with its ledger and backing.
+        Self::db(1, 1, v, 3, 0)
+    }
+}
+
+// For backwards compatibility and tests.
+impl WeightInfo for () {
+    fn become_validator() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::become_validator()
+    }
+    fn stake() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::stake()
+    }
+    fn stake_more(n: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::stake_more(n)
+    }
+    fn unbond(n: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::unbond(n)
+    }
+    fn withdraw_unbonded(u: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::withdraw_unbonded(u)
+    }
+    fn stop_validating(m: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::stop_validating(m)
+    }
+    fn chill(n: u32, m: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::chill(n, m)
+    }
+    fn force_add_validator() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::force_add_validator()
+    }
+    fn force_remove_validator(m: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::force_remove_validator(m)
+    }
+    fn nominate(n: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::nominate(n)
+    }
+    fn slash(n: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::slash(n)
+    }
+    fn set_min_stake() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::set_min_stake()
+    }
+    fn new_era(s: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::new_era(s)
+    }
+    fn bond_for() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::bond_for()
+    }
+    fn set_controller() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::set_controller()
+    }
+    fn validate() -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::validate()
+    }
+    fn force_election(v: u32) -> Weight {
+        DbWeightEstimate::<RocksDbWeight>::force_election(v)
+    }
+}
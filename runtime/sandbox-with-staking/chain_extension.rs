@@ -1,33 +1,498 @@
+use core::marker::PhantomData;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use frame_support::{
+    sp_runtime::DispatchError,
+    sp_std::vec::Vec,
+    traits::Get,
+    weights::Weight,
+    Hashable,
+};
+use ink_sandbox::sp_externalities::{decl_extension, with_externalities, ExternalitiesExt};
 use pallet_contracts::chain_extension::{
-    ChainExtension, Config as ContractsConfig, Environment, Ext, InitState, RetVal,
+    ChainExtension, Config as ContractsConfig, Environment, Ext, InitState,
+    RegisteredChainExtension, RetVal,
 };
-use pallet_fake_staking::{Config as FakeStakingConfig, Pallet as FakeStakingPallet};
-use parity_scale_codec::Encode;
+use pallet_fake_staking::{StakingError, StakingInterface};
+use parity_scale_codec::{Decode, DecodeAll, Encode, MaxEncodedLen};
 
-#[derive(Default)]
-pub struct StakingExtension;
+/// Status code returned when the extension has executed the function successfully.
+pub const SUCCESS: u32 = 0;
+
+/// The maximum number of accounts that can be queried by a single batch function.
+pub const MAX_BATCH_SIZE: u32 = 64;
+
+/// Errors that the extension reports to the contracts through the status code.
+///
+/// The discriminants are the status codes themselves, so they are part of the contract-facing API
+/// and must never change. The contract side mirrors this enum in
+/// `extension_errors::StakingExtensionError`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum StakingExtensionError {
+    /// The contract has called a function that the extension does not know.
+    UnknownFunction = 1,
+    /// The staking system has rejected the call for a reason not covered by the other variants.
+    DispatchFailed = 2,
+    /// The input of the function could not be decoded.
+    DecodingFailed = 3,
+    /// The stake is not enough to become a staker.
+    NotEnoughStake = 4,
+    /// The account is already a staker.
+    AlreadyStaking = 5,
+    /// The account is not a staker.
+    NotStaker = 6,
+    /// The free balance of the account is not enough to cover the stake.
+    InsufficientBalance = 7,
+    /// More than [`MAX_BATCH_SIZE`] accounts have been passed to a batch function.
+    TooManyAccounts = 8,
+}
+
+impl StakingExtensionError {
+    /// Translate an error returned by the staking system `Staking`.
+    pub fn from_dispatch_error<Staking: StakingInterface>(error: DispatchError) -> Self {
+        match Staking::staking_error(&error) {
+            Some(StakingError::NotEnoughStake) => Self::NotEnoughStake,
+            Some(StakingError::AlreadyStaking) => Self::AlreadyStaking,
+            Some(StakingError::NotStaker) => Self::NotStaker,
+            Some(StakingError::InsufficientBalance) => Self::InsufficientBalance,
+            None => Self::DispatchFailed,
+        }
+    }
+
+    /// The status code under which the error is reported to the contract.
+    pub fn status_code(self) -> u32 {
+        self as u32
+    }
+}
+
+/// The weight of copying and decoding a single byte of the input.
+pub const WEIGHT_PER_INPUT_BYTE: Weight = Weight::from_parts(1_000, 0);
+
+/// The weight charged for calling a function that the extension does not know. No work is done
+/// besides reporting the error, but the call must not be free either.
+pub const UNKNOWN_FUNCTION_WEIGHT: Weight = Weight::from_parts(100_000, 0);
 
-impl<Runtime: ContractsConfig + FakeStakingConfig> ChainExtension<Runtime> for StakingExtension {
+/// The weight charged for calling the function `func_id` of the extension over `Staking` with
+/// `input_len` bytes of input: the storage accesses done by the function plus the cost of its
+/// input. `None` for unknown functions, which are charged [`UNKNOWN_FUNCTION_WEIGHT`] instead.
+pub fn weight_of<Runtime, Staking>(func_id: u16, input_len: u32) -> Option<Weight>
+where
+    Runtime: frame_system::Config,
+    Staking: StakingInterface,
+{
+    // The number of accounts passed to a batch function. The length prefix is shorter than an
+    // account, so it does not count.
+    let accounts = u64::from(input_len)
+        / (<Runtime as frame_system::Config>::AccountId::max_encoded_len() as u64).max(1);
+
+    let work = match func_id {
+        // Every query reads a single storage item: `ActiveValidators`, `MinStakeOverride`,
+        // `TotalStake`, `CounterForStakers`, `Validators`, `Validators`, `Stakers` and
+        // `ActiveValidators` respectively.
+        41..=48 => <Runtime as frame_system::Config>::DbWeight::get().reads(1),
+        // The state-mutating functions cost as much as the corresponding staking calls.
+        49 => Staking::stake_weight(),
+        50 => Staking::stake_more_weight(),
+        // The batch queries read `ActiveValidators` and `Stakers` respectively once per account.
+        51 | 52 => <Runtime as frame_system::Config>::DbWeight::get().reads(accounts),
+        _ => return None,
+    };
+    Some(work.saturating_add(WEIGHT_PER_INPUT_BYTE.saturating_mul(input_len.into())))
+}
+
+/// Chain extension that exposes the staking system `Staking` to the contracts.
+pub struct StakingExtension<Staking>(PhantomData<Staking>);
+
+impl<Staking> Default for StakingExtension<Staking> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Runtime, Staking> RegisteredChainExtension<Runtime> for StakingExtension<Staking>
+where
+    Runtime: ContractsConfig,
+    Staking: StakingInterface<AccountId = <Runtime as frame_system::Config>::AccountId>,
+    Staking::Balance: Encode + Decode,
+    Staking::ValidatorPrefs: Encode,
+{
+    const ID: u16 = 0;
+}
+
+impl<Runtime, Staking> ChainExtension<Runtime> for StakingExtension<Staking>
+where
+    Runtime: ContractsConfig,
+    Staking: StakingInterface<AccountId = <Runtime as frame_system::Config>::AccountId>,
+    Staking::Balance: Encode + Decode,
+    Staking::ValidatorPrefs: Encode,
+{
     fn call<E: Ext<T = Runtime>>(
         &mut self,
         env: Environment<E, InitState>,
     ) -> pallet_contracts::chain_extension::Result<RetVal> {
-        // Ensure that the chain extension is called with the correct function ID (41).
-        assert_eq!(env.func_id(), 41);
+        let func_id = env.func_id();
 
         // We are going to use the `buf_in_buf_out` environment, which allows us to read from the
         // input buffer and write to the output buffer.
         let mut env = env.buf_in_buf_out();
+        let caller = env.ext().address().clone();
+
+        // Charge for the work before doing it, so that the contract cannot get it for free.
+        let Some(weight) = weight_of::<Runtime, Staking>(func_id, env.in_len()) else {
+            env.charge_weight(UNKNOWN_FUNCTION_WEIGHT)?;
+            // Do not trap the caller: let the contract decide what to do with an unknown function.
+            let status = StakingExtensionError::UnknownFunction.status_code();
+            record_call(caller.encode(), func_id, Vec::new(), Vec::new(), status);
+            return Ok(RetVal::Converging(status));
+        };
+        env.charge_weight(weight)?;
+
+        // Read the raw input, so that a malformed one is reported to the contract instead of
+        // trapping it.
+        let input = env.read(env.in_len())?;
+
+        // A response mocked by the test takes precedence over the staking system.
+        let response = mocked_response(func_id, &input)
+            .unwrap_or_else(|| Self::dispatch(func_id, &input, caller.clone()));
+
+        let (output, status) = match response {
+            Ok(result) => {
+                // Write the result to the output buffer.
+                env.write(&result, false, None)?;
+                // Return `Converging(SUCCESS)` to indicate that the chain extension executed
+                // successfully.
+                (result, SUCCESS)
+            }
+            Err(error) => (Vec::new(), error.status_code()),
+        };
+
+        record_call(caller.encode(), func_id, input, output, status);
+        Ok(RetVal::Converging(status))
+    }
+}
+
+impl<Staking> StakingExtension<Staking>
+where
+    Staking: StakingInterface,
+    Staking::AccountId: Decode,
+    Staking::Balance: Encode + Decode,
+    Staking::ValidatorPrefs: Encode,
+{
+    /// Execute the function `func_id` for the contract `caller` and return its encoded output.
+    ///
+    /// This is what `call` does once the weight is charged, without the mocks and the recorder,
+    /// so tests can drive the extension with any `Staking` system, e.g. a mock.
+    pub fn dispatch(
+        func_id: u16,
+        input: &[u8],
+        caller: Staking::AccountId,
+    ) -> Result<Vec<u8>, StakingExtensionError> {
+        let output = match func_id {
+            // `is_validator(account: AccountId) -> bool`
+            41 => {
+                // Decode the account ID from the input.
+                let account = decode::<Staking::AccountId>(input)?;
+                // Check if the account is a validator.
+                Staking::is_validator(&account).encode()
+            }
+            // `min_stake() -> u128`
+            42 => Staking::minimum_stake().encode(),
+            // `total_stake() -> u128`
+            43 => Staking::total_stake().encode(),
+            // `staker_count() -> u32`
+            44 => Staking::staker_count().encode(),
+            // `validator_prefs(account: AccountId) -> Option<ValidatorPrefs>`
+            45 => Staking::validator_prefs(&decode(input)?).encode(),
+            // `is_validator_candidate(account: AccountId) -> bool`
+            46 => Staking::is_validator_candidate(&decode(input)?).encode(),
+            // `stake_of(account: AccountId) -> Option<u128>`
+            47 => Staking::active_stake(&decode(input)?).encode(),
+            // `validator_count() -> u32`
+            48 => Staking::validator_count().encode(),
+            // `stake(amount: u128)`, on behalf of the calling contract
+            49 => {
+                Staking::stake(&caller, decode(input)?)
+                    .map_err(StakingExtensionError::from_dispatch_error::<Staking>)?;
+                Vec::new()
+            }
+            // `stake_more(amount: u128)`, on behalf of the calling contract
+            50 => {
+                Staking::stake_more(&caller, decode(input)?)
+                    .map_err(StakingExtensionError::from_dispatch_error::<Staking>)?;
+                Vec::new()
+            }
+            // `are_validators(accounts: Vec<AccountId>) -> Vec<bool>`
+            51 => decode_batch::<Staking::AccountId>(input)?
+                .iter()
+                .map(Staking::is_validator)
+                .collect::<Vec<_>>()
+                .encode(),
+            // `stakes_of(accounts: Vec<AccountId>) -> Vec<Option<u128>>`
+            52 => decode_batch::<Staking::AccountId>(input)?
+                .iter()
+                .map(Staking::active_stake)
+                .collect::<Vec<_>>()
+                .encode(),
+            _ => return Err(StakingExtensionError::UnknownFunction),
+        };
+        Ok(output)
+    }
+}
+
+/// A single call to the [`StakingExtension`], as seen by the [`StakingCallRecorder`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RecordedCall {
+    /// The encoded account of the contract that has called the extension.
+    pub caller: Vec<u8>,
+    /// The called function.
+    pub func_id: u16,
+    /// The encoded arguments of the function (empty for unknown functions).
+    pub input: Vec<u8>,
+    /// The encoded value returned to the contract (empty if the call has failed).
+    pub output: Vec<u8>,
+    /// The status code returned to the contract.
+    pub status: u32,
+}
+
+impl RecordedCall {
+    /// The contract that has called the extension, decoded as `T`.
+    pub fn caller_as<T: Decode>(&self) -> Option<T> {
+        T::decode_all(&mut &self.caller[..]).ok()
+    }
+
+    /// The arguments of the function, decoded as `T`.
+    pub fn input_as<T: Decode>(&self) -> Option<T> {
+        T::decode_all(&mut &self.input[..]).ok()
+    }
+
+    /// The value returned to the contract, decoded as `T`.
+    pub fn output_as<T: Decode>(&self) -> Option<T> {
+        T::decode_all(&mut &self.output[..]).ok()
+    }
+}
+
+/// The log of the calls to the [`StakingExtension`], shared between the [`StakingCallRecorder`]
+/// and the test that inspects it.
+#[derive(Clone, Default, Debug)]
+pub struct RecordedCalls(Arc<Mutex<Vec<RecordedCall>>>);
+
+impl RecordedCalls {
+    /// All the calls recorded so far, oldest first.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.0.lock().expect("Recorder lock is poisoned").clone()
+    }
+
+    /// Forget all the calls recorded so far.
+    pub fn clear(&self) {
+        self.0.lock().expect("Recorder lock is poisoned").clear()
+    }
+
+    fn push(&self, call: RecordedCall) {
+        self.0.lock().expect("Recorder lock is poisoned").push(call)
+    }
+}
+
+decl_extension! {
+    /// Opt-in recorder of the calls to the [`StakingExtension`]. Register it with
+    /// `Sandbox::register_extension` and keep a clone of its [`RecordedCalls`] to inspect them.
+    pub struct StakingCallRecorder(RecordedCalls);
+}
+
+impl StakingCallRecorder {
+    /// Create a recorder that appends the calls to `calls`.
+    pub fn new(calls: RecordedCalls) -> Self {
+        Self(calls)
+    }
+}
+
+/// Append the call to the log of the [`StakingCallRecorder`], if one is registered.
+fn record_call(caller: Vec<u8>, func_id: u16, input: Vec<u8>, output: Vec<u8>, status: u32) {
+    with_externalities(|ext| {
+        if let Some(recorder) = ext.extension::<StakingCallRecorder>() {
+            recorder.0.push(RecordedCall {
+                caller,
+                func_id,
+                input,
+                output,
+                status,
+            });
+        }
+    });
+}
+
+/// The responses of the [`StakingExtension`] overridden by a test, shared between the
+/// [`StakingExtensionMocker`] and the test that configures it.
+///
+/// A response is mocked either for a function called with specific arguments, or for all the calls
+/// of a function. The former takes precedence.
+#[derive(Clone, Default, Debug)]
+pub struct StakingExtensionMocks(
+    Arc<Mutex<BTreeMap<(u16, Option<Vec<u8>>), Result<Vec<u8>, StakingExtensionError>>>>,
+);
+
+impl StakingExtensionMocks {
+    /// Make the function `func_id` return `output` when called with `args`, e.g.
+    /// `mock_output(41, BOB, true)` to treat `BOB` as a validator. Use `()` for functions without
+    /// arguments.
+    pub fn mock_output(&self, func_id: u16, args: impl Encode, output: impl Encode) {
+        self.mock(func_id, Some(args.encode()), Ok(output.encode()))
+    }
+
+    /// Make the function `func_id` return `output`, whatever the arguments.
+    pub fn mock_output_for_all(&self, func_id: u16, output: impl Encode) {
+        self.mock(func_id, None, Ok(output.encode()))
+    }
+
+    /// Make the function `func_id` fail with `error` when called with `args`.
+    pub fn mock_error(&self, func_id: u16, args: impl Encode, error: StakingExtensionError) {
+        self.mock(func_id, Some(args.encode()), Err(error))
+    }
+
+    /// Make the function `func_id` fail with `error`, whatever the arguments, e.g.
+    /// `mock_error_for_all(47, StakingExtensionError::DispatchFailed)` to make `stake_of` fail.
+    pub fn mock_error_for_all(&self, func_id: u16, error: StakingExtensionError) {
+        self.mock(func_id, None, Err(error))
+    }
+
+    /// Forget all the mocked responses: the staking system answers again.
+    pub fn clear(&self) {
+        self.0.lock().expect("Mocks lock is poisoned").clear()
+    }
+
+    fn mock(
+        &self,
+        func_id: u16,
+        input: Option<Vec<u8>>,
+        response: Result<Vec<u8>, StakingExtensionError>,
+    ) {
+        self.0
+            .lock()
+            .expect("Mocks lock is poisoned")
+            .insert((func_id, input), response);
+    }
+
+    fn response(
+        &self,
+        func_id: u16,
+        input: &[u8],
+    ) -> Option<Result<Vec<u8>, StakingExtensionError>> {
+        let mocks = self.0.lock().expect("Mocks lock is poisoned");
+        mocks
+            .get(&(func_id, Some(input.to_vec())))
+            .or_else(|| mocks.get(&(func_id, None)))
+            .cloned()
+    }
+}
+
+decl_extension! {
+    /// Opt-in registry of the mocked responses of the [`StakingExtension`]. Register it with
+    /// `Sandbox::register_extension` (or `SandboxWithStaking::mock_staking_extension`) and keep a
+    /// clone of its [`StakingExtensionMocks`] to configure them.
+    pub struct StakingExtensionMocker(StakingExtensionMocks);
+}
+
+impl StakingExtensionMocker {
+    /// Create a registry that answers with the responses from `mocks`.
+    pub fn new(mocks: StakingExtensionMocks) -> Self {
+        Self(mocks)
+    }
+}
+
+/// The mocked response for the call, if a [`StakingExtensionMocker`] is registered and has one.
+fn mocked_response(func_id: u16, input: &[u8]) -> Option<Result<Vec<u8>, StakingExtensionError>> {
+    with_externalities(|ext| {
+        ext.extension::<StakingExtensionMocker>()
+            .and_then(|mocker| mocker.0.response(func_id, input))
+    })
+    .flatten()
+}
+
+/// Decode the whole `input` as a single `T`.
+fn decode<T: Decode>(mut input: &[u8]) -> Result<T, StakingExtensionError> {
+    T::decode_all(&mut input).map_err(|_| StakingExtensionError::DecodingFailed)
+}
+
+/// Decode the whole `input` as a vector of at most [`MAX_BATCH_SIZE`] items.
+fn decode_batch<T: Decode>(input: &[u8]) -> Result<Vec<T>, StakingExtensionError> {
+    let items = decode::<Vec<T>>(input)?;
+    if items.len() > MAX_BATCH_SIZE as usize {
+        return Err(StakingExtensionError::TooManyAccounts);
+    }
+    Ok(items)
+}
+
+/// Errors that the randomness extension reports to the contracts through the status code. The codes
+/// are the same as the ones of the corresponding [`StakingExtensionError`]s.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum RandomnessExtensionError {
+    /// The contract has called a function that the extension does not know.
+    UnknownFunction = 1,
+    /// The input of the function could not be decoded.
+    DecodingFailed = 3,
+}
+
+impl RandomnessExtensionError {
+    /// The status code under which the error is reported to the contract.
+    pub fn status_code(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Chain extension that provides the contracts with deterministic randomness: the same subject
+/// gives the same value within a block, but different values in different blocks.
+///
+/// It is NOT secure: the values are known in advance to anyone who knows the parent block.
+#[derive(Default)]
+pub struct RandomnessExtension;
+
+impl RandomnessExtension {
+    /// The weight charged for the `random` function with `input_len` bytes of input: reading the
+    /// block number and the parent hash plus the cost of its input.
+    pub fn weight_of<Runtime: frame_system::Config>(input_len: u32) -> Weight {
+        <Runtime as frame_system::Config>::DbWeight::get()
+            .reads(2)
+            .saturating_add(WEIGHT_PER_INPUT_BYTE.saturating_mul(input_len.into()))
+    }
+}
+
+impl<Runtime: ContractsConfig> RegisteredChainExtension<Runtime> for RandomnessExtension {
+    const ID: u16 = 1;
+}
+
+impl<Runtime: ContractsConfig> ChainExtension<Runtime> for RandomnessExtension {
+    fn call<E: Ext<T = Runtime>>(
+        &mut self,
+        env: Environment<E, InitState>,
+    ) -> pallet_contracts::chain_extension::Result<RetVal> {
+        let func_id = env.func_id();
+        let mut env = env.buf_in_buf_out();
+
+        // `random(subject: [u8; 32]) -> [u8; 32]` is the only function of the extension.
+        if func_id != 1 {
+            env.charge_weight(UNKNOWN_FUNCTION_WEIGHT)?;
+            return Ok(RetVal::Converging(RandomnessExtensionError::UnknownFunction.status_code()));
+        }
+
+        env.charge_weight(RandomnessExtension::weight_of::<Runtime>(env.in_len()))?;
 
-        // Read the account ID from the input buffer.
-        let account = env.read_as::<<Runtime as frame_system::Config>::AccountId>()?;
-        // Check if the account is a validator.
-        let result = FakeStakingPallet::<Runtime>::is_validator(account);
+        let input = env.read(env.in_len())?;
+        let Ok(subject) = <[u8; 32]>::decode_all(&mut &input[..]) else {
+            return Ok(RetVal::Converging(RandomnessExtensionError::DecodingFailed.status_code()));
+        };
 
-        // Write the result to the output buffer.
-        env.write(&result.encode(), false, None)?;
+        let random = (
+            frame_system::Pallet::<Runtime>::parent_hash(),
+            frame_system::Pallet::<Runtime>::block_number(),
+            subject,
+        )
+            .blake2_256();
 
-        // Return `Converging(0)` to indicate that the chain extension executed successfully.
-        Ok(RetVal::Converging(0))
+        env.write(&random.encode(), false, None)?;
+        Ok(RetVal::Converging(SUCCESS))
     }
 }
@@ -1,14 +1,16 @@
-mod chain_extension;
+pub mod chain_extension;
 
 use frame_support::{
     __private::TestExternalities,
     construct_runtime, derive_impl, parameter_types,
-    sp_runtime::{testing::H256, traits::Convert, AccountId32, Perbill},
-    traits::{ConstBool, ConstU128, ConstU32, ConstU64, Currency, Randomness},
-    weights::Weight,
+    sp_runtime::{testing::H256, traits::Convert, AccountId32, BuildStorage, Perbill},
+    traits::{ConstBool, ConstU128, ConstU32, ConstU64, Currency, Hooks, Randomness},
+    weights::{constants::RocksDbWeight, Weight},
 };
 use ink_sandbox::{AccountIdFor, BlockBuilder, Extension, RuntimeMetadataPrefixed, Sandbox};
-use crate::chain_extension::StakingExtension;
+use crate::chain_extension::{
+    RandomnessExtension, StakingExtension, StakingExtensionMocker, StakingExtensionMocks,
+};
 
 construct_runtime!(
     pub enum RuntimeWithStaking {
@@ -25,6 +27,7 @@ impl frame_system::Config for RuntimeWithStaking {
     type Block = frame_system::mocking::MockBlockU32<RuntimeWithStaking>;
     type Version = ();
     type BlockHashCount = ConstU32<250>;
+    type DbWeight = RocksDbWeight;
     type AccountData =
         pallet_balances::AccountData<<RuntimeWithStaking as pallet_balances::Config>::Balance>;
 }
@@ -76,6 +79,7 @@ parameter_types! {
     pub DefaultDepositLimit: BalanceOf = 10_000_000;
     pub CodeHashLockupDepositPercent: Perbill = Perbill::from_percent(0);
     pub MaxDelegateDependencies: u32 = 32;
+    pub const MinValidatorStake: Option<u128> = None;
 }
 
 impl pallet_contracts::Config for RuntimeWithStaking {
@@ -87,7 +91,7 @@ impl pallet_contracts::Config for RuntimeWithStaking {
     type CallFilter = frame_support::traits::Everything;
     type WeightPrice = Self;
     type WeightInfo = ();
-    type ChainExtension = StakingExtension;
+    type ChainExtension = (StakingExtension<FakeStaking>, RandomnessExtension);
     type Schedule = SandboxSchedule;
     type CallStack = [pallet_contracts::Frame<Self>; 5];
     type DepositPerByte = ConstU128<1>;
@@ -108,7 +112,23 @@ impl pallet_contracts::Config for RuntimeWithStaking {
     type ApiVersion = ();
 }
 
-impl pallet_fake_staking::Config for RuntimeWithStaking {}
+impl pallet_fake_staking::Config for RuntimeWithStaking {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type MinStake = ConstU128<100>;
+    type BondingDuration = ConstU32<10>;
+    type MaxUnlockingChunks = ConstU32<32>;
+    type MaxValidators = ConstU32<100>;
+    type MinValidatorStake = MinValidatorStake;
+    type MaxNominations = ConstU32<16>;
+    type MaxNominators = ConstU32<256>;
+    type EraDuration = ConstU32<10>;
+    type RewardPerEra = ConstU128<1_000>;
+    type MaxStakers = ConstU32<1_024>;
+    type ValidatorCount = ConstU32<10>;
+    type WeightInfo = ();
+}
 
 // Implement `crate::Sandbox` trait
 
@@ -120,11 +140,40 @@ pub struct SandboxWithStaking {
     ext: TestExternalities,
 }
 
+impl SandboxWithStaking {
+    /// Creates a new sandbox with the state built from the given genesis configuration.
+    pub fn new(genesis: RuntimeGenesisConfig) -> Self {
+        let storage = genesis
+            .build_storage()
+            .expect("Failed to build genesis storage");
+        let mut ext = TestExternalities::new(storage);
+        ext.execute_with(|| Self::initialize_block(1, Default::default()));
+        Self { ext }
+    }
+
+    /// The default genesis configuration: only `DEFAULT_ACCOUNT` is endowed (with
+    /// `INITIAL_BALANCE`) and there are neither validators nor stakers.
+    pub fn default_genesis() -> RuntimeGenesisConfig {
+        RuntimeGenesisConfig {
+            balances: pallet_balances::GenesisConfig {
+                balances: vec![(DEFAULT_ACCOUNT, INITIAL_BALANCE)],
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Register a fresh [`StakingExtensionMocker`] (dropping the previously mocked responses) and
+    /// return the handle through which the test can mock the `StakingExtension` responses.
+    pub fn mock_staking_extension(&mut self) -> StakingExtensionMocks {
+        let mocks = StakingExtensionMocks::default();
+        self.register_extension(StakingExtensionMocker::new(mocks.clone()));
+        mocks
+    }
+}
+
 impl Default for SandboxWithStaking {
     fn default() -> Self {
-        let ext =
-            BlockBuilder::<RuntimeWithStaking>::new_ext(vec![(DEFAULT_ACCOUNT, INITIAL_BALANCE)]);
-        Self { ext }
+        Self::new(Self::default_genesis())
     }
 }
 
@@ -156,12 +205,17 @@ impl Sandbox for SandboxWithStaking {
         height: frame_system::pallet_prelude::BlockNumberFor<Self::Runtime>,
         parent_hash: <Self::Runtime as frame_system::Config>::Hash,
     ) {
-        BlockBuilder::<Self::Runtime>::initialize_block(height, parent_hash)
+        BlockBuilder::<Self::Runtime>::initialize_block(height, parent_hash);
+        // `BlockBuilder` knows only about the standard pallets, so we have to run our hooks.
+        FakeStaking::on_initialize(height);
     }
 
     fn finalize_block(
         height: frame_system::pallet_prelude::BlockNumberFor<Self::Runtime>,
     ) -> <Self::Runtime as frame_system::Config>::Hash {
+        // Catch any corrupted staking state as soon as the block is over.
+        #[cfg(feature = "try-runtime")]
+        FakeStaking::try_state(height).expect("FakeStaking invariants are violated");
         BlockBuilder::<Self::Runtime>::finalize_block(height)
     }
 